@@ -0,0 +1,78 @@
+//! Times scope resolution in deeply nested code, where looking up a name declared far out walks the
+//! whole scope chain unless the result is memoized. Run with `cargo bench --bench resolution`.
+
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use full_moon::ast::{Block, Stmt};
+use luahint::config::Config;
+use luahint::scope::ScopeManager;
+
+/// How deeply the generated functions are nested.
+const DEPTH: usize = 100;
+/// How many calls each generated function makes.
+const CALLS: usize = 20;
+const RUNS: u32 = 10;
+
+/// Functions nested `DEPTH` deep, each calling a function declared outside all of them `CALLS`
+/// times.
+fn source() -> String {
+    let mut source = String::from("local function target(a, b) end\n");
+    for depth in 0..DEPTH {
+        source.push_str(&format!("local function f{depth}(x{depth})\n"));
+        for _ in 0..CALLS {
+            source.push_str(&format!("target(x{depth}, {depth})\n"));
+        }
+    }
+    source.push_str(&"end\n".repeat(DEPTH));
+    source
+}
+
+/// The block of the innermost generated function.
+fn innermost(block: &Block) -> &Block {
+    block
+        .stmts()
+        .find_map(|stmt| match stmt {
+            Stmt::LocalFunction(function) => Some(innermost(function.body().block())),
+            _ => None,
+        })
+        .unwrap_or(block)
+}
+
+fn time<T>(runs: u32, mut f: impl FnMut() -> T) -> Duration {
+    let start = Instant::now();
+    for _ in 0..runs {
+        std::hint::black_box(f());
+    }
+    start.elapsed() / runs
+}
+
+fn main() {
+    let source = source();
+    let ast = Arc::new(full_moon::parse(&source).unwrap());
+    let config = Arc::new(Config::from_value(serde_json::json!({ "stdlib": false })).0);
+
+    let build = time(RUNS, || {
+        ScopeManager::new(ast.clone(), config.clone(), Arc::default()).build()
+    });
+    println!("build, {DEPTH} scopes deep with {CALLS} calls each: {build:?}");
+
+    // The parameters of the outer functions are never looked up from the innermost scope while
+    // building, so the first lookup of each walks the chain and the second is memoized.
+    let manager = ScopeManager::new(ast.clone(), config, Arc::default()).build();
+    let scope = manager
+        .get_scope_id(innermost(manager.ast().nodes()))
+        .unwrap();
+    let names = (0..DEPTH)
+        .map(|depth| format!("x{depth}"))
+        .collect::<Vec<_>>();
+    let lookups = |manager: &ScopeManager| {
+        for name in &names {
+            std::hint::black_box(manager.find_var_from(scope, name));
+        }
+    };
+    let uncached = time(1, || lookups(&manager));
+    let cached = time(RUNS, || lookups(&manager));
+    println!("{DEPTH} lookups from the innermost scope, uncached: {uncached:?}");
+    println!("{DEPTH} lookups from the innermost scope, cached: {cached:?}");
+}
@@ -0,0 +1,129 @@
+-- Signatures of the commonly used parts of the Neovim Lua API, loaded by luahint when the
+-- `neovimApi` setting is enabled. Only parameter names matter here, so bodies are empty.
+
+-- vim.api
+
+function vim.api.nvim_buf_add_highlight(buffer, ns_id, hl_group, line, col_start, col_end) end
+function vim.api.nvim_buf_attach(buffer, send_buffer, opts) end
+function vim.api.nvim_buf_call(buffer, fun) end
+function vim.api.nvim_buf_clear_namespace(buffer, ns_id, line_start, line_end) end
+function vim.api.nvim_buf_create_user_command(buffer, name, command, opts) end
+function vim.api.nvim_buf_del_extmark(buffer, ns_id, id) end
+function vim.api.nvim_buf_del_keymap(buffer, mode, lhs) end
+function vim.api.nvim_buf_del_var(buffer, name) end
+function vim.api.nvim_buf_delete(buffer, opts) end
+function vim.api.nvim_buf_get_extmarks(buffer, ns_id, start, end_, opts) end
+function vim.api.nvim_buf_get_keymap(buffer, mode) end
+function vim.api.nvim_buf_get_lines(buffer, start, end_, strict_indexing) end
+function vim.api.nvim_buf_get_mark(buffer, name) end
+function vim.api.nvim_buf_get_name(buffer) end
+function vim.api.nvim_buf_get_option(buffer, name) end
+function vim.api.nvim_buf_get_text(buffer, start_row, start_col, end_row, end_col, opts) end
+function vim.api.nvim_buf_get_var(buffer, name) end
+function vim.api.nvim_buf_is_loaded(buffer) end
+function vim.api.nvim_buf_is_valid(buffer) end
+function vim.api.nvim_buf_line_count(buffer) end
+function vim.api.nvim_buf_set_extmark(buffer, ns_id, line, col, opts) end
+function vim.api.nvim_buf_set_keymap(buffer, mode, lhs, rhs, opts) end
+function vim.api.nvim_buf_set_lines(buffer, start, end_, strict_indexing, replacement) end
+function vim.api.nvim_buf_set_name(buffer, name) end
+function vim.api.nvim_buf_set_option(buffer, name, value) end
+function vim.api.nvim_buf_set_text(buffer, start_row, start_col, end_row, end_col, replacement) end
+function vim.api.nvim_buf_set_var(buffer, name, value) end
+function vim.api.nvim_call_function(fn, args) end
+function vim.api.nvim_cmd(cmd, opts) end
+function vim.api.nvim_command(command) end
+function vim.api.nvim_create_augroup(name, opts) end
+function vim.api.nvim_create_autocmd(event, opts) end
+function vim.api.nvim_create_buf(listed, scratch) end
+function vim.api.nvim_create_namespace(name) end
+function vim.api.nvim_create_user_command(name, command, opts) end
+function vim.api.nvim_del_augroup_by_id(id) end
+function vim.api.nvim_del_augroup_by_name(name) end
+function vim.api.nvim_del_autocmd(id) end
+function vim.api.nvim_del_keymap(mode, lhs) end
+function vim.api.nvim_del_user_command(name) end
+function vim.api.nvim_del_var(name) end
+function vim.api.nvim_echo(chunks, history, opts) end
+function vim.api.nvim_err_writeln(str) end
+function vim.api.nvim_eval(expr) end
+function vim.api.nvim_exec2(src, opts) end
+function vim.api.nvim_exec_autocmds(event, opts) end
+function vim.api.nvim_feedkeys(keys, mode, escape_ks) end
+function vim.api.nvim_get_autocmds(opts) end
+function vim.api.nvim_get_hl(ns_id, opts) end
+function vim.api.nvim_get_keymap(mode) end
+function vim.api.nvim_get_option_value(name, opts) end
+function vim.api.nvim_get_runtime_file(name, all) end
+function vim.api.nvim_get_var(name) end
+function vim.api.nvim_notify(msg, log_level, opts) end
+function vim.api.nvim_open_win(buffer, enter, config) end
+function vim.api.nvim_out_write(str) end
+function vim.api.nvim_paste(data, crlf, phase) end
+function vim.api.nvim_put(lines, type, after, follow) end
+function vim.api.nvim_replace_termcodes(str, from_part, do_lt, special) end
+function vim.api.nvim_set_current_buf(buffer) end
+function vim.api.nvim_set_current_line(line) end
+function vim.api.nvim_set_current_win(window) end
+function vim.api.nvim_set_hl(ns_id, name, val) end
+function vim.api.nvim_set_keymap(mode, lhs, rhs, opts) end
+function vim.api.nvim_set_option_value(name, value, opts) end
+function vim.api.nvim_set_var(name, value) end
+function vim.api.nvim_win_call(window, fun) end
+function vim.api.nvim_win_close(window, force) end
+function vim.api.nvim_win_get_buf(window) end
+function vim.api.nvim_win_get_config(window) end
+function vim.api.nvim_win_get_cursor(window) end
+function vim.api.nvim_win_get_height(window) end
+function vim.api.nvim_win_get_option(window, name) end
+function vim.api.nvim_win_get_width(window) end
+function vim.api.nvim_win_is_valid(window) end
+function vim.api.nvim_win_set_buf(window, buffer) end
+function vim.api.nvim_win_set_config(window, config) end
+function vim.api.nvim_win_set_cursor(window, pos) end
+function vim.api.nvim_win_set_height(window, height) end
+function vim.api.nvim_win_set_hl_ns(window, ns_id) end
+function vim.api.nvim_win_set_option(window, name, value) end
+function vim.api.nvim_win_set_width(window, width) end
+
+-- vim.fn
+
+function vim.fn.bufnr(buf, create) end
+function vim.fn.col(expr, winid) end
+function vim.fn.executable(expr) end
+function vim.fn.exists(expr) end
+function vim.fn.expand(string, nosuf, list) end
+function vim.fn.feedkeys(string, mode) end
+function vim.fn.filereadable(file) end
+function vim.fn.fnamemodify(fname, mods) end
+function vim.fn.getcwd(winnr, tabnr) end
+function vim.fn.getline(lnum, end_) end
+function vim.fn.glob(expr, nosuf, list, alllinks) end
+function vim.fn.has(feature) end
+function vim.fn.input(prompt, text, completion) end
+function vim.fn.isdirectory(directory) end
+function vim.fn.jobstart(cmd, opts) end
+function vim.fn.jobstop(id) end
+function vim.fn.line(expr, winid) end
+function vim.fn.mkdir(name, flags, prot) end
+function vim.fn.readfile(fname, type, max) end
+function vim.fn.setline(lnum, text) end
+function vim.fn.stdpath(what) end
+function vim.fn.system(cmd, input) end
+function vim.fn.writefile(object, fname, flags) end
+
+-- vim
+
+function vim.cmd(command) end
+function vim.defer_fn(fn, timeout) end
+function vim.endswith(s, suffix) end
+function vim.inspect(object, options) end
+function vim.keymap.del(modes, lhs, opts) end
+function vim.keymap.set(mode, lhs, rhs, opts) end
+function vim.notify(msg, level, opts) end
+function vim.schedule(fn) end
+function vim.split(s, sep, opts) end
+function vim.startswith(s, prefix) end
+function vim.tbl_deep_extend(behavior, ...) end
+function vim.tbl_extend(behavior, ...) end
+function vim.validate(spec) end
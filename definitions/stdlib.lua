@@ -0,0 +1,81 @@
+-- Signatures of the Lua standard library, loaded by luahint unless the `stdlib` setting is
+-- disabled. Parameter names follow the reference manual. Only parameter names matter here, so
+-- bodies are empty.
+
+-- basic functions
+
+function assert(v, message) end
+function collectgarbage(opt, arg) end
+function dofile(filename) end
+function error(message, level) end
+function getmetatable(object) end
+function ipairs(t) end
+function load(chunk, chunkname, mode, env) end
+function loadfile(filename, mode, env) end
+function next(table, index) end
+function pairs(t) end
+function pcall(f, ...) end
+function rawequal(v1, v2) end
+function rawget(table, index) end
+function rawlen(v) end
+function rawset(table, index, value) end
+function select(index, ...) end
+function setmetatable(table, metatable) end
+function tonumber(e, base) end
+function tostring(v) end
+function type(v) end
+function xpcall(f, msgh, ...) end
+
+-- string
+
+function string.byte(s, i, j) end
+function string.char(...) end
+function string.dump(f, strip) end
+function string.find(s, pattern, init, plain) end
+function string.format(formatstring, ...) end
+function string.gmatch(s, pattern) end
+function string.gsub(s, pattern, repl, n) end
+function string.len(s) end
+function string.lower(s) end
+function string.match(s, pattern, init) end
+function string.pack(fmt, v1, ...) end
+function string.packsize(fmt) end
+function string.rep(s, n, sep) end
+function string.reverse(s) end
+function string.sub(s, i, j) end
+function string.unpack(fmt, s, pos) end
+function string.upper(s) end
+
+-- table
+
+function table.concat(list, sep, i, j) end
+-- Usually called as `table.insert(list, value)`, so `pos` isn't named to avoid mislabeling it.
+function table.insert(list, ...) end
+function table.move(a1, f, e, t, a2) end
+function table.pack(...) end
+function table.remove(list, pos) end
+function table.sort(list, comp) end
+function table.unpack(list, i, j) end
+
+-- math
+
+function math.abs(x) end
+function math.ceil(x) end
+function math.cos(x) end
+function math.deg(x) end
+function math.exp(x) end
+function math.floor(x) end
+function math.fmod(x, y) end
+function math.log(x, base) end
+function math.max(x, ...) end
+function math.min(x, ...) end
+function math.modf(x) end
+function math.rad(x) end
+function math.random(m, n) end
+function math.randomseed(x) end
+function math.sin(x) end
+function math.sqrt(x) end
+function math.tan(x) end
+function math.tointeger(x) end
+function math.type(x) end
+function math.ult(m, n) end
@@ -9,6 +9,7 @@ local state = {}
 ---@field update? string[] | string Autocommands that should trigger a refresh
 ---@field root_dir? fun(): string Function to determine the root directory of the project
 ---@field enabled_at_startup? boolean Whether to enable the plugin at startup
+---@field server? table Settings passed to the language server as initialization options
 local default = {
 	---@type string | string[]
 	update = {
@@ -19,6 +20,7 @@ local default = {
 	},
 	enabled_at_startup = true,
 	root_dir = vim.fn.getcwd,
+	server = vim.empty_dict(),
 }
 
 ---@type number Namespace id
@@ -27,7 +29,14 @@ local namespace = vim.api.nvim_create_namespace("luahint")
 ---@param id number LSP Client ID
 ---@param buf number Buffer number
 local function fetch_hints(id, buf)
-	local params = vim.lsp.util.make_range_params(0, "utf-8")
+	-- Hints are drawn for the whole buffer, not just around the cursor.
+	local params = {
+		textDocument = vim.lsp.util.make_text_document_params(buf or 0),
+		range = {
+			start = { line = 0, character = 0 },
+			["end"] = { line = vim.api.nvim_buf_line_count(buf or 0), character = 0 },
+		},
+	}
 
 	local client = vim.lsp.get_client_by_id(id)
 
@@ -38,9 +47,16 @@ local function fetch_hints(id, buf)
 	local handler = function(e, res)
 		if res and not e then
 			for i, hint in ipairs(res) do
+				local label = hint.label
+				if hint.paddingLeft then
+					label = " " .. label
+				end
+				if hint.paddingRight then
+					label = label .. " "
+				end
 				local opts = {
 					id = i,
-					virt_text = { { hint.kind == 1 and (": " .. hint.label) or (hint.label .. ": "), "LspInlayHint" } },
+					virt_text = { { label, "LspInlayHint" } },
 					virt_text_pos = "inline",
 				}
 				vim.api.nvim_buf_set_extmark(
@@ -97,6 +113,7 @@ function M.setup(opts)
 				name = "luahint",
 				cmd = { "luahint" },
 				root_dir = opts.root_dir(),
+				init_options = opts.server,
 			})
 			vim.lsp.buf_attach_client(buf, client_id)
 
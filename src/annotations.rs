@@ -0,0 +1,127 @@
+use full_moon::tokenizer::{Token, TokenType};
+
+/// A single `---@param name type` annotation.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParamAnnotation {
+    pub name: String,
+    pub ty: String,
+    /// Whether the name was written `name?`, marking the parameter as optional.
+    pub optional: bool,
+}
+
+/// EmmyLua annotations from the comments directly preceding a function definition.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Annotations {
+    pub params: Vec<ParamAnnotation>,
+}
+
+impl Annotations {
+    /// Collects annotations from the leading trivia of a definition's first token.
+    pub fn parse<'a>(trivia: impl IntoIterator<Item = &'a Token>) -> Self {
+        let params = trivia
+            .into_iter()
+            .filter_map(|token| match token.token_type() {
+                TokenType::SingleLineComment { comment } => parse_param(comment),
+                _ => None,
+            })
+            .collect();
+        Self { params }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.params.is_empty()
+    }
+
+    pub fn param(&self, name: &str) -> Option<&ParamAnnotation> {
+        self.params.iter().find(|param| param.name == name)
+    }
+
+    /// Whether a parameter is annotated as some kind of table, e.g. `table`, `table<K, V>`,
+    /// `string[]` or a `{...}` shape.
+    pub fn is_table(&self, name: &str) -> bool {
+        self.param(name).is_some_and(|param| {
+            let ty = param.ty.trim_end_matches('?');
+            ty == "table" || ty.starts_with("table<") || ty.starts_with('{') || ty.ends_with("[]")
+        })
+    }
+
+    /// Whether a parameter may be left out, either by naming it `name?` or by giving it a type
+    /// like `string?` or `string|nil`.
+    pub fn is_optional(&self, name: &str) -> bool {
+        self.param(name).is_some_and(|param| {
+            param.optional
+                || param.ty.ends_with('?')
+                || param.ty.split('|').any(|ty| ty.trim() == "nil")
+        })
+    }
+
+    /// The field names of a parameter annotated with a table shape, like `{width: number, height}`.
+    pub fn table_fields(&self, name: &str) -> Option<Vec<String>> {
+        let ty = self.param(name)?.ty.strip_prefix('{')?.strip_suffix('}')?;
+        let fields = split_top_level(ty)
+            .into_iter()
+            .filter_map(|field| {
+                let name = field.split(':').next()?.trim().trim_end_matches('?');
+                (!name.is_empty()).then(|| name.to_string())
+            })
+            .collect::<Vec<_>>();
+        (!fields.is_empty()).then_some(fields)
+    }
+}
+
+/// Parses the text of a `---@param name type [description]` comment, without the leading `--`.
+fn parse_param(comment: &str) -> Option<ParamAnnotation> {
+    let rest = comment
+        .strip_prefix('-')?
+        .trim_start()
+        .strip_prefix("@param")?;
+    if !rest.starts_with(char::is_whitespace) {
+        return None;
+    }
+    let rest = rest.trim_start();
+    let (name, rest) = rest.split_once(char::is_whitespace)?;
+    let rest = rest.trim_start();
+    let ty = if rest.starts_with('{') {
+        let mut depth = 0;
+        let end = rest.char_indices().find_map(|(i, c)| {
+            match c {
+                '{' => depth += 1,
+                '}' => depth -= 1,
+                _ => {}
+            }
+            (depth == 0).then_some(i + 1)
+        })?;
+        &rest[..end]
+    } else {
+        rest.split_whitespace().next()?
+    };
+    let (name, optional) = match name.strip_suffix('?') {
+        Some(name) => (name, true),
+        None => (name, false),
+    };
+    Some(ParamAnnotation {
+        name: name.to_string(),
+        ty: ty.to_string(),
+        optional,
+    })
+}
+
+/// Splits on commas that aren't nested inside braces, parentheses or angle brackets.
+fn split_top_level(text: &str) -> Vec<&str> {
+    let mut parts = vec![];
+    let mut depth = 0;
+    let mut start = 0;
+    for (i, c) in text.char_indices() {
+        match c {
+            '{' | '(' | '<' => depth += 1,
+            '}' | ')' | '>' => depth -= 1,
+            ',' if depth == 0 => {
+                parts.push(&text[start..i]);
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    parts.push(&text[start..]);
+    parts
+}
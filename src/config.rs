@@ -0,0 +1,227 @@
+use std::collections::HashMap;
+
+use regex::Regex;
+use schemars::JsonSchema;
+use serde::Deserialize;
+
+/// Server settings, read from the client's `initializationOptions`.
+#[derive(Debug, Clone, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase", default)]
+pub struct Config {
+    /// Master switch for all hints. When off, documents aren't analyzed at all.
+    pub enabled: bool,
+    /// The Lua dialect of the workspace. This is a check, not a switch: the syntax the parser
+    /// accepts is fixed by the features luahint is built with, and setting a dialect they don't
+    /// cover only produces a warning. Documents are parsed the same way whatever its value.
+    pub language: Option<Language>,
+    /// Names of functions whose calls never receive hints.
+    pub ignored_callees: Vec<String>,
+    /// Regular expressions matched against callee names; matching calls receive no hints.
+    pub ignored_callee_patterns: Vec<String>,
+    /// Only hint calls to functions declaring at least this many named parameters.
+    pub min_params_for_hints: usize,
+    /// Only hint calls passing at least this many arguments, so that calls like `print(x)` can be
+    /// left alone.
+    pub min_args_for_hints: usize,
+    /// Only hint the first this many arguments of a call. A trailing `...` parameter doesn't
+    /// count towards it.
+    pub max_parameter_hints: Option<usize>,
+    /// With `maxParameterHints`, show a `…` hint after the hint of the last hinted argument.
+    pub mark_truncated_hints: bool,
+    /// Hint parameters named `_` or starting with `_`, which conventionally mark them as unused.
+    pub hint_underscore_params: bool,
+    /// Skip hints on arguments that are a variable named like the parameter, as in `f(count)` for
+    /// `function f(count)`. Names are compared ignoring ASCII case.
+    pub suppress_matching_names: bool,
+    /// Replacement labels for parameter names, e.g. `configuration` to `cfg`.
+    pub abbreviations: HashMap<String, String>,
+    /// Prefix parameter hints with the called function's name, e.g. `move.x` instead of `x`. Only
+    /// the function's own name is used, so calls to `M.move` and `obj:move` are labeled `move.x`
+    /// too.
+    pub label_include_function: bool,
+    /// How parameter hints are written, with `{name}` standing for the parameter, e.g. `{name}:`
+    /// or `{name} =`.
+    pub label_format: String,
+    /// Pad hints on `true`/`false` arguments, which are the hardest to read positionally.
+    pub emphasize_boolean_args: bool,
+    /// Ask the client to put a space before parameter hints.
+    pub padding_left: bool,
+    /// Ask the client to put a space between parameter hints and their argument.
+    pub padding_right: bool,
+    /// Only hint calls to functions the document exports through its final `return`.
+    pub hint_exported_only: bool,
+    /// Warn when a function taking several parameters is called with a single table, which
+    /// usually means the arguments were meant to be passed positionally.
+    pub warn_single_table_argument: bool,
+    /// Warn about calls passing more arguments than a function declares, or fewer than it
+    /// requires. Lua doesn't mind either, so this is off by default. Variadic functions are never
+    /// checked, and parameters annotated as optional aren't required.
+    pub check_arity: bool,
+    /// Show a `self` hint on the receiver of method calls like `obj:method()`, which is otherwise
+    /// left implicit.
+    pub show_self_hint: bool,
+    /// Show the kind of value (`table`, `string`, ...) after locals initialized by calling a
+    /// function that returns a literal.
+    pub show_return_kinds: bool,
+    /// Follow `setmetatable` with an inline metatable, so calling a table with a `__call`
+    /// metamethod is hinted with that function's parameters.
+    pub infer_metatables: bool,
+    /// Resolve calls against bundled signatures of the standard library: the basic functions like
+    /// `pcall` and the `string`, `table` and `math` libraries. Functions declared in the document
+    /// take precedence over them.
+    pub stdlib: bool,
+    /// Resolve calls against the bundled definitions of the Neovim Lua API (`vim.api`, `vim.fn`,
+    /// ...).
+    pub neovim_api: bool,
+    /// Documents larger than this are not analyzed, as parsing them would dominate latency.
+    pub max_file_size_bytes: usize,
+    /// How many required modules to keep analyzed in memory before evicting the least recently
+    /// used ones.
+    pub max_cached_modules: usize,
+    /// How many open documents keep their parse and scope tree cached. The caches of the least
+    /// recently hinted documents are dropped beyond this, their text is kept.
+    pub max_cached_documents: usize,
+    /// `ignored_callee_patterns`, compiled once when the configuration is loaded.
+    #[serde(skip)]
+    ignored_callee_regexes: Vec<Regex>,
+}
+
+/// A dialect of Lua, named after the feature that enables its syntax.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum Language {
+    Lua51,
+    Lua52,
+    Lua53,
+    Lua54,
+    Luau,
+}
+
+impl Language {
+    pub fn name(self) -> &'static str {
+        match self {
+            Language::Lua51 => "lua51",
+            Language::Lua52 => "lua52",
+            Language::Lua53 => "lua53",
+            Language::Lua54 => "lua54",
+            Language::Luau => "luau",
+        }
+    }
+
+    /// Whether the parser was built to accept this dialect's syntax.
+    pub fn is_supported(self) -> bool {
+        match self {
+            Language::Lua51 => true,
+            Language::Lua52 => cfg!(feature = "lua52"),
+            Language::Lua53 => cfg!(feature = "lua53"),
+            Language::Lua54 => cfg!(feature = "lua54"),
+            Language::Luau => cfg!(feature = "luau"),
+        }
+    }
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            language: None,
+            ignored_callees: vec![],
+            ignored_callee_patterns: vec![],
+            min_params_for_hints: 0,
+            min_args_for_hints: 1,
+            max_parameter_hints: None,
+            mark_truncated_hints: false,
+            hint_underscore_params: false,
+            suppress_matching_names: false,
+            abbreviations: HashMap::new(),
+            label_include_function: false,
+            label_format: "{name}:".to_string(),
+            emphasize_boolean_args: false,
+            padding_left: false,
+            padding_right: true,
+            hint_exported_only: false,
+            warn_single_table_argument: false,
+            check_arity: false,
+            show_self_hint: false,
+            show_return_kinds: false,
+            infer_metatables: false,
+            stdlib: true,
+            neovim_api: false,
+            max_file_size_bytes: 2 * 1024 * 1024,
+            max_cached_modules: 256,
+            max_cached_documents: 64,
+            ignored_callee_regexes: vec![],
+        }
+    }
+}
+
+/// Merges the settings in `update` into `settings`, for clients that only send the settings that
+/// changed. Settings missing from `update` keep their value and `null` resets one to its default.
+/// An update that isn't an object is ignored.
+pub fn merge_settings(settings: &mut serde_json::Value, update: serde_json::Value) {
+    let serde_json::Value::Object(update) = update else {
+        return;
+    };
+    if !settings.is_object() {
+        *settings = serde_json::Value::Object(Default::default());
+    }
+    let serde_json::Value::Object(settings) = settings else {
+        return;
+    };
+    for (key, value) in update {
+        if value.is_null() {
+            settings.remove(&key);
+        } else {
+            settings.insert(key, value);
+        }
+    }
+}
+
+impl Config {
+    /// Parses the configuration, falling back to defaults for anything that can't be used. Problems
+    /// are returned as human readable warnings rather than failing the whole configuration.
+    pub fn from_value(value: serde_json::Value) -> (Self, Vec<String>) {
+        let mut warnings = vec![];
+        let mut config = match serde_json::from_value::<Config>(value) {
+            Ok(config) => config,
+            Err(e) => {
+                warnings.push(format!("invalid luahint configuration: {e}"));
+                Config::default()
+            }
+        };
+        if let Some(language) = config.language.filter(|language| !language.is_supported()) {
+            let name = language.name();
+            warnings.push(format!(
+                "luahint was built without {name} syntax support, rebuild it with `--features {name}`"
+            ));
+        }
+        if !config.label_format.contains("{name}") {
+            warnings.push(format!(
+                "labelFormat `{}` doesn't contain `{{name}}`, using `{{name}}:`",
+                config.label_format
+            ));
+            config.label_format = Config::default().label_format;
+        }
+        config.ignored_callee_regexes = config
+            .ignored_callee_patterns
+            .iter()
+            .filter_map(|pattern| match Regex::new(pattern) {
+                Ok(regex) => Some(regex),
+                Err(e) => {
+                    warnings.push(format!("ignoring invalid callee pattern `{pattern}`: {e}"));
+                    None
+                }
+            })
+            .collect();
+        (config, warnings)
+    }
+
+    /// Whether calls to the function named `name` should be left without hints.
+    pub fn is_ignored_callee(&self, name: &str) -> bool {
+        self.ignored_callees.iter().any(|callee| callee == name)
+            || self
+                .ignored_callee_regexes
+                .iter()
+                .any(|regex| regex.is_match(name))
+    }
+}
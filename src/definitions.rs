@@ -0,0 +1,46 @@
+use std::sync::{Arc, OnceLock};
+
+use crate::config::Config;
+use crate::modules::Exports;
+use crate::scope::ScopeManager;
+
+/// Stub definitions of the Neovim Lua API, shipped with the server.
+const NEOVIM: &str = include_str!("../definitions/neovim.lua");
+
+/// Stub definitions of the `string`, `table` and `math` libraries and the basic functions.
+const STDLIB: &str = include_str!("../definitions/stdlib.lua");
+
+/// The globals declared by the bundled Neovim API definitions, keyed by their dotted name.
+pub fn neovim() -> &'static Exports {
+    static DEFINITIONS: OnceLock<Exports> = OnceLock::new();
+    DEFINITIONS.get_or_init(|| load(NEOVIM))
+}
+
+/// The globals declared by the bundled standard library definitions, keyed by their dotted name.
+pub fn stdlib() -> &'static Exports {
+    static DEFINITIONS: OnceLock<Exports> = OnceLock::new();
+    DEFINITIONS.get_or_init(|| load(STDLIB))
+}
+
+/// Collects the globals declared by a stub file.
+fn load(source: &str) -> Exports {
+    let Ok(ast) = full_moon::parse(source) else {
+        return Exports::new();
+    };
+    // Stubs are read without any bundled definitions, which would otherwise be loaded while
+    // loading themselves.
+    let config = Config {
+        stdlib: false,
+        neovim_api: false,
+        ..Config::default()
+    };
+    let manager = ScopeManager::new(ast, Arc::new(config), Arc::default()).build();
+    let Some(global) = manager.stack.first().copied() else {
+        return Exports::new();
+    };
+    manager.scopes[global]
+        .var_names
+        .iter()
+        .filter_map(|(name, var)| Some((name.clone(), manager.var_value(global, *var)?.clone())))
+        .collect()
+}
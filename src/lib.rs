@@ -0,0 +1,49 @@
+//! Parameter inlay hints for Lua. The language server is built on [`ScopeManager`], which can also
+//! be used directly to compute hints for a source string.
+
+use std::sync::Arc;
+
+use lsp_types::{Diagnostic, InlayHint};
+
+use crate::config::Config;
+use crate::modules::Modules;
+use crate::scope::ScopeManager;
+
+pub mod annotations;
+pub mod config;
+pub mod definitions;
+pub mod lsp;
+pub mod modules;
+pub mod reparse;
+pub mod scope;
+pub mod symbols;
+pub mod visitor;
+pub mod workspace;
+
+/// Computes the parameter hints for a Lua source string with the default configuration. Positions
+/// are 1-based, as full_moon reports them.
+pub fn hints_for_source(source: &str) -> Result<Vec<InlayHint>, full_moon::Error> {
+    hints_for_source_with(source, Arc::default(), Arc::default())
+}
+
+/// Like [`hints_for_source`], with the given configuration and the modules `require` calls are
+/// resolved against.
+pub fn hints_for_source_with(
+    source: &str,
+    config: Arc<Config>,
+    modules: Arc<Modules>,
+) -> Result<Vec<InlayHint>, full_moon::Error> {
+    let ast = full_moon::parse(source)?;
+    Ok(ScopeManager::new(ast, config, modules).build().hints)
+}
+
+/// The warnings about calls in a Lua source string, for the checks enabled in `config`. Unlike
+/// hints, their ranges are 0-based, as published to the client.
+pub fn diagnostics_for_source_with(
+    source: &str,
+    config: Arc<Config>,
+    modules: Arc<Modules>,
+) -> Result<Vec<Diagnostic>, full_moon::Error> {
+    let ast = full_moon::parse(source)?;
+    Ok(ScopeManager::new(ast, config, modules).build().diagnostics)
+}
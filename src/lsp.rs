@@ -1,19 +1,50 @@
-use std::sync::atomic::{AtomicI32, Ordering};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, AtomicI32, AtomicU32, AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Arc, RwLock};
+use std::time::{Duration, Instant};
 
 use crop::Rope;
 use dashmap::DashMap;
+use full_moon::ast::Ast;
 use lsp_types::{InitializeParams, InitializeResult, ServerInfo, Url};
+use serde::{Deserialize, Serialize};
+use tokio::sync::Notify;
 
 use tower_lsp::jsonrpc::{self, Result};
 use tower_lsp::lsp_types::*;
 use tower_lsp::{Client, LanguageServer};
 
+use crate::config::{merge_settings, Config};
+use crate::modules::Modules;
+use crate::reparse::Reparse;
 use crate::scope::ScopeManager;
+use crate::symbols::document_symbols;
+use crate::visitor::is_identifier;
+use crate::workspace::WorkspaceIndex;
+
+/// Documents with at least this many lines get server-initiated progress reports while their hints
+/// are computed, so the client shows activity instead of appearing to hang.
+const PROGRESS_LINE_THRESHOLD: usize = 5_000;
+
+/// How long a document must go without edits before its hints are computed, so that a burst of
+/// keystrokes is analyzed once rather than once per keystroke.
+const HINT_DEBOUNCE: Duration = Duration::from_millis(50);
+
+/// How many times hints are computed for a request when the document keeps changing meanwhile.
+const MAX_HINT_ATTEMPTS: usize = 3;
 
 #[derive(Debug)]
 pub struct Doc {
     text: Rope,
     version: AtomicI32,
+    /// Whether the user was told this document is too large to analyze.
+    size_notified: AtomicBool,
+    /// The last parse of the text.
+    parsed: RwLock<Option<Parsed>>,
+    /// When the text was last edited, if it was since it was opened.
+    edited_at: Option<Instant>,
+    /// When hints were last requested for the document, as a tick of [`Backend::hint_clock`].
+    hinted: AtomicU64,
     #[allow(unused)]
     uri: Url,
 }
@@ -24,15 +55,206 @@ impl Doc {
             text: Rope::from(text),
             uri,
             version: AtomicI32::new(version),
+            size_notified: AtomicBool::new(false),
+            parsed: RwLock::new(None),
+            edited_at: None,
+            hinted: AtomicU64::new(0),
+        }
+    }
+
+    pub fn version(&self) -> i32 {
+        self.version.load(Ordering::Relaxed)
+    }
+
+    /// How much of the last parse can be reused for the current text.
+    pub fn reparse(&self) -> Reparse {
+        let parsed = self.parsed.read().unwrap();
+        match parsed.as_ref() {
+            Some(parsed) if parsed.version == self.version() && parsed.edited_from.is_none() => {
+                Reparse::Current(parsed.ast.clone())
+            }
+            Some(Parsed {
+                ast,
+                edited_from: Some(edited_from),
+                ..
+            }) => Reparse::Edited(ast.clone(), *edited_from),
+            _ => Reparse::Full,
+        }
+    }
+
+    /// The scope tree built for the current text with `config`, if a request already built one
+    /// and none of the modules it requires changed since.
+    pub fn manager(&self, config: &Arc<Config>, generation: u64) -> Option<Arc<ScopeManager>> {
+        let parsed = self.parsed.read().unwrap();
+        let parsed = parsed
+            .as_ref()
+            .filter(|parsed| parsed.version == self.version() && parsed.edited_from.is_none())?;
+        let (built, manager) = parsed.manager.as_ref()?;
+        (*built == generation
+            && Arc::ptr_eq(&manager.config, config)
+            && manager.dependencies_current())
+        .then(|| manager.clone())
+    }
+
+    /// Whether a parse of the text is cached, current or not.
+    pub fn is_parsed(&self) -> bool {
+        self.parsed.read().unwrap().is_some()
+    }
+
+    /// Drops the cached parse and scope tree. The text is kept, and parsed again when needed.
+    pub fn drop_parse(&self) {
+        *self.parsed.write().unwrap() = None;
+    }
+
+    /// Caches the scope tree built for `version` at the module `generation`, along with the parse
+    /// it was built from.
+    pub fn cache_manager(&self, version: i32, generation: u64, manager: Arc<ScopeManager>) {
+        *self.parsed.write().unwrap() = Some(Parsed {
+            ast: manager.ast.clone(),
+            version,
+            edited_from: None,
+            manager: Some((generation, manager)),
+        });
+    }
+}
+
+/// A parse of a document's text, kept so that requests don't parse text that hasn't changed.
+#[derive(Debug)]
+struct Parsed {
+    ast: Arc<Ast>,
+    version: i32,
+    /// The first byte edited since the parse. The text before it is unchanged.
+    edited_from: Option<usize>,
+    /// The scope tree built from the parse, shared by the requests for its version, and the
+    /// generation of the modules it was built with.
+    manager: Option<(u64, Arc<ScopeManager>)>,
+}
+
+/// Converts an LSP position, whose character counts UTF-16 code units, to a byte offset in `text`.
+/// Positions past the end of a line or of the text are clamped to it.
+pub fn position_to_byte(text: &Rope, position: Position) -> usize {
+    let line = position.line as usize;
+    if line >= text.line_len() {
+        return text.byte_len();
+    }
+    let mut units = 0;
+    let mut bytes = 0;
+    for c in text.line(line).chars() {
+        if units >= position.character as usize {
+            break;
+        }
+        units += c.len_utf16();
+        bytes += c.len_utf8();
+    }
+    text.byte_of_line(line) + bytes
+}
+
+/// Applies the edits of a `didChange` notification to `text`, returning the offset of the first
+/// byte they changed. Changes are applied in the order they were sent, each to the text left by the
+/// ones before it, as their ranges refer to it. Sorting them by offset would misplace the changes
+/// whose ranges already account for earlier ones.
+pub fn apply_changes(text: &mut Rope, changes: Vec<TextDocumentContentChangeEvent>) -> usize {
+    let mut edited_from = usize::MAX;
+    for change in changes {
+        let Some(range) = change.range else {
+            continue;
+        };
+        let start_byte = position_to_byte(text, range.start);
+        let end_byte = position_to_byte(text, range.end);
+        edited_from = edited_from.min(start_byte);
+        text.replace(start_byte..end_byte, change.text);
+    }
+    edited_from
+}
+
+/// Parameters of the `luahint/hints` request, which computes hints for a source string without
+/// involving any open document.
+#[derive(Debug, Deserialize)]
+pub struct HintsParams {
+    pub source: String,
+    /// Settings to use instead of the server's configuration.
+    #[serde(default)]
+    pub config: Option<serde_json::Value>,
+    #[serde(default)]
+    pub order: HintOrder,
+}
+
+/// How the hints of a `luahint/hints` response are ordered.
+#[derive(Debug, Default, Clone, Copy, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum HintOrder {
+    /// By position in the source.
+    #[default]
+    Document,
+    /// Grouped by the name of the called function, then by position. Hints for calls whose callee
+    /// has no name come last.
+    ByFunction,
+}
+
+#[derive(Debug, Serialize)]
+pub struct HintsResult {
+    pub hints: Vec<PlainHint>,
+}
+
+/// An inlay hint without the LSP-specific structure.
+#[derive(Debug, Serialize)]
+pub struct PlainHint {
+    pub line: u32,
+    pub character: u32,
+    pub label: String,
+    pub kind: Option<&'static str>,
+    /// The name of the called function, if it has one.
+    pub function: Option<String>,
+}
+
+impl From<InlayHint> for PlainHint {
+    fn from(hint: InlayHint) -> Self {
+        Self {
+            line: hint.position.line,
+            character: hint.position.character,
+            label: match hint.label {
+                InlayHintLabel::String(label) => label,
+                InlayHintLabel::LabelParts(parts) => {
+                    parts.into_iter().map(|part| part.value).collect()
+                }
+            },
+            kind: hint.kind.map(|kind| match kind {
+                InlayHintKind::TYPE => "type",
+                _ => "parameter",
+            }),
+            function: hint
+                .data
+                .as_ref()
+                .and_then(|data| data.get("function"))
+                .and_then(|function| function.as_str())
+                .map(str::to_owned),
         }
     }
 }
 
 #[derive(Debug)]
 pub struct Backend {
-    #[allow(unused)]
     client: Client,
     documents: DashMap<Url, Doc>,
+    /// The settings received from the client so far, merged, which `config` is parsed from.
+    settings: RwLock<serde_json::Value>,
+    config: RwLock<Arc<Config>>,
+    modules: Arc<Modules>,
+    /// The exported functions of the workspace folders' modules, for definitions in other files.
+    index: Arc<WorkspaceIndex>,
+    /// Whether the client accepts server-initiated `window/workDoneProgress/create` requests.
+    work_done_progress: AtomicBool,
+    /// Whether the client lets the server register for `workspace/didChangeWatchedFiles`.
+    watch_files: AtomicBool,
+    next_progress_id: AtomicU32,
+    /// Set by `shutdown`, after which work waiting for edits to settle is dropped.
+    shut_down: AtomicBool,
+    /// Wakes the work waiting for edits to settle when the server shuts down.
+    shutting_down: Notify,
+    /// How many times the text of a document was parsed.
+    parses: AtomicUsize,
+    /// Counts the hint requests, ordering the documents by when they were last hinted.
+    hint_clock: AtomicU64,
 }
 
 impl Backend {
@@ -40,13 +262,384 @@ impl Backend {
         Self {
             client,
             documents: DashMap::new(),
+            settings: RwLock::new(serde_json::Value::Object(Default::default())),
+            config: RwLock::new(Arc::new(Config::default())),
+            modules: Arc::default(),
+            index: Arc::default(),
+            work_done_progress: AtomicBool::new(false),
+            watch_files: AtomicBool::new(false),
+            next_progress_id: AtomicU32::new(0),
+            shut_down: AtomicBool::new(false),
+            shutting_down: Notify::new(),
+            parses: AtomicUsize::new(0),
+            hint_clock: AtomicU64::new(0),
         }
     }
+
+    /// How many times the text of a document was parsed, rather than reused from a parse of the
+    /// same text, since the server started.
+    pub fn parses(&self) -> usize {
+        self.parses.load(Ordering::Relaxed)
+    }
+
+    /// Counts the parse `reparse` is about to do, if it does one.
+    fn count_parse(&self, reparse: &Reparse) {
+        if !matches!(reparse, Reparse::Current(_)) {
+            self.parses.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    /// Drops the cached parses of the least recently hinted documents other than `uri`, which was
+    /// just cached, so that at most `limit` documents keep one.
+    fn evict_parses(&self, uri: &Url, limit: usize) {
+        let mut cached = self
+            .documents
+            .iter()
+            .filter(|doc| doc.key() != uri && doc.is_parsed())
+            .map(|doc| (doc.hinted.load(Ordering::Relaxed), doc.key().clone()))
+            .collect::<Vec<_>>();
+        let excess = (cached.len() + 1).saturating_sub(limit);
+        cached.sort_unstable();
+        for (_, uri) in cached.into_iter().take(excess) {
+            if let Some(doc) = self.documents.get(&uri) {
+                doc.drop_parse();
+            }
+        }
+    }
+
+    /// Starts a work done progress, reusing the client's token when one was provided and otherwise
+    /// creating one if `create` is set and the client supports it.
+    async fn begin_progress(
+        &self,
+        token: Option<ProgressToken>,
+        create: bool,
+        title: &str,
+    ) -> Option<ProgressToken> {
+        let token = match token {
+            Some(token) => token,
+            None if create && self.work_done_progress.load(Ordering::Relaxed) => {
+                let id = self.next_progress_id.fetch_add(1, Ordering::Relaxed);
+                let token = ProgressToken::String(format!("luahint/{id}"));
+                self.client
+                    .send_request::<request::WorkDoneProgressCreate>(WorkDoneProgressCreateParams {
+                        token: token.clone(),
+                    })
+                    .await
+                    .ok()?;
+                token
+            }
+            None => return None,
+        };
+        self.client
+            .send_notification::<notification::Progress>(ProgressParams {
+                token: token.clone(),
+                value: ProgressParamsValue::WorkDone(WorkDoneProgress::Begin(
+                    WorkDoneProgressBegin {
+                        title: title.to_owned(),
+                        cancellable: Some(false),
+                        message: None,
+                        percentage: None,
+                    },
+                )),
+            })
+            .await;
+        Some(token)
+    }
+
+    /// Handles `luahint/hints`.
+    pub async fn hints(&self, params: HintsParams) -> Result<HintsResult> {
+        let config = match params.config {
+            Some(config) => {
+                let (config, warnings) = Config::from_value(config);
+                for warning in warnings {
+                    self.client.log_message(MessageType::WARNING, warning).await;
+                }
+                Arc::new(config)
+            }
+            None => self.config(),
+        };
+        let modules = self.modules.clone();
+        let source = params.source;
+        let hints = tokio::task::spawn_blocking(move || {
+            crate::hints_for_source_with(&source, config, modules)
+                .map_err(|e| jsonrpc::Error::invalid_params(e.to_string()))
+        })
+        .await
+        .map_err(|_| jsonrpc::Error::internal_error())??;
+
+        let mut hints = hints.into_iter().map(PlainHint::from).collect::<Vec<_>>();
+        match params.order {
+            HintOrder::Document => hints.sort_by_key(|hint| (hint.line, hint.character)),
+            HintOrder::ByFunction => hints.sort_by(|a, b| {
+                (a.function.is_none(), &a.function, a.line, a.character).cmp(&(
+                    b.function.is_none(),
+                    &b.function,
+                    b.line,
+                    b.character,
+                ))
+            }),
+        }
+        Ok(HintsResult { hints })
+    }
+
+    /// Handles the `luahint.validateScopes` command, which takes the URI of an open document and
+    /// returns the problems found in its scope tree.
+    async fn validate_scopes(&self, arguments: Vec<serde_json::Value>) -> Result<Vec<String>> {
+        let uri = arguments
+            .into_iter()
+            .next()
+            .and_then(|uri| serde_json::from_value::<Url>(uri).ok())
+            .ok_or_else(|| jsonrpc::Error::invalid_params("expected a document URI"))?;
+        let Some(doc) = self.documents.get(&uri) else {
+            return Err(jsonrpc::Error::invalid_params(format!("{uri} is not open")));
+        };
+        let config = self.config();
+        if doc.text.byte_len() > config.max_file_size_bytes {
+            return Err(jsonrpc::Error::invalid_params(format!(
+                "{uri} is larger than maxFileSizeBytes ({} bytes)",
+                config.max_file_size_bytes
+            )));
+        }
+        let text = doc.text.to_string();
+        drop(doc);
+        let modules = self.modules.clone();
+        tokio::task::spawn_blocking(move || {
+            full_moon::parse(&text)
+                .map(|ast| ScopeManager::new(ast, config, modules).build().validate())
+                .map_err(|e| jsonrpc::Error::invalid_params(e.to_string()))
+        })
+        .await
+        .map_err(|_| jsonrpc::Error::internal_error())?
+    }
+
+    fn config(&self) -> Arc<Config> {
+        self.config.read().unwrap().clone()
+    }
+
+    /// Merges the settings in `value` into the ones received before and applies them, logging any
+    /// problems with them.
+    async fn set_config(&self, value: serde_json::Value) {
+        let settings = {
+            let mut settings = self.settings.write().unwrap();
+            merge_settings(&mut settings, value);
+            settings.clone()
+        };
+        let (config, warnings) = Config::from_value(settings);
+        self.modules.set_capacity(config.max_cached_modules);
+        *self.config.write().unwrap() = Arc::new(config);
+        for warning in warnings {
+            self.client.log_message(MessageType::WARNING, warning).await;
+        }
+    }
+
+    /// Indexes the workspace folders `roots` in the background, so the server can answer requests
+    /// meanwhile.
+    fn index_roots(&self, roots: Vec<PathBuf>) {
+        let index = self.index.clone();
+        tokio::task::spawn_blocking(move || index.scan(&roots));
+    }
+
+    /// Indexes the file at `uri` again in the background, after it changed on disk.
+    fn reindex(&self, uri: &Url) {
+        let Ok(path) = uri.to_file_path() else {
+            return;
+        };
+        let index = self.index.clone();
+        let roots = self.modules.roots();
+        tokio::task::spawn_blocking(move || index.update_file(&roots, &path));
+    }
+
+    /// Waits until the document at `uri` hasn't been edited for [`HINT_DEBOUNCE`]. Returns false
+    /// if the server shuts down meanwhile, in which case the waiting work should be dropped.
+    async fn settle(&self, uri: &Url) -> bool {
+        loop {
+            // Registered before the flag is checked, so a shutdown in between still wakes it.
+            let shutting_down = self.shutting_down.notified();
+            if self.shut_down.load(Ordering::Relaxed) {
+                return false;
+            }
+            let Some(edited_at) = self.documents.get(uri).and_then(|doc| doc.edited_at) else {
+                return true;
+            };
+            let elapsed = edited_at.elapsed();
+            if elapsed >= HINT_DEBOUNCE {
+                return true;
+            }
+            tokio::select! {
+                _ = tokio::time::sleep(HINT_DEBOUNCE - elapsed) => {}
+                _ = shutting_down => return false,
+            }
+        }
+    }
+
+    /// The scope tree of the document at `uri` and the version it was built for. It's built once
+    /// per version and configuration, and shared by the requests made for them. Returns `None` if
+    /// the document isn't open, is larger than `maxFileSizeBytes`, or nothing of it parsed, as with
+    /// a syntax error in a document that never parsed as a whole.
+    async fn scope_manager(
+        &self,
+        uri: &Url,
+        config: Arc<Config>,
+    ) -> Result<Option<(i32, Arc<ScopeManager>)>> {
+        let Some(doc) = self.documents.get(uri) else {
+            return Ok(None);
+        };
+        if doc.text.byte_len() > config.max_file_size_bytes {
+            return Ok(None);
+        }
+        let version = doc.version();
+        let generation = self.modules.generation();
+        if let Some(manager) = doc.manager(&config, generation) {
+            return Ok(Some((version, manager)));
+        }
+        let text = doc.text.to_string();
+        let reparse = doc.reparse();
+        self.count_parse(&reparse);
+        let modules = self.modules.clone();
+        let limit = config.max_cached_documents;
+        // Release the map guard before awaiting so `did_change` can't deadlock on this entry.
+        drop(doc);
+
+        // The pass is CPU-bound, so run it off the async executor to keep the server responsive.
+        let manager = tokio::task::spawn_blocking(move || {
+            let ast = reparse.parse(&text)?;
+            Some(Arc::new(ScopeManager::new(ast, config, modules).build()))
+        })
+        .await
+        .map_err(|_| jsonrpc::Error::internal_error())?;
+        let Some(manager) = manager else {
+            return Ok(None);
+        };
+        if let Some(doc) = self
+            .documents
+            .get(uri)
+            .filter(|doc| doc.version() == version)
+        {
+            doc.cache_manager(version, generation, manager.clone());
+            drop(doc);
+            self.evict_parses(uri, limit);
+        }
+        Ok(Some((version, manager)))
+    }
+
+    /// The scope tree of the document at `uri` if it's still at `version`, the one a request's
+    /// position refers to.
+    async fn manager_at(
+        &self,
+        uri: &Url,
+        version: i32,
+        config: Arc<Config>,
+    ) -> Result<Option<Arc<ScopeManager>>> {
+        Ok(self
+            .scope_manager(uri, config)
+            .await?
+            .filter(|(built, _)| *built == version)
+            .map(|(_, manager)| manager))
+    }
+
+    /// Computes the hints within `range` of the document at `uri`. Returns `None` if the document
+    /// was edited meanwhile, as the hints' positions no longer match it.
+    async fn compute_hints(
+        &self,
+        uri: &Url,
+        range: Range,
+        config: Arc<Config>,
+    ) -> Result<Option<Vec<InlayHint>>> {
+        let Some((version, manager)) = self.scope_manager(uri, config).await? else {
+            return Ok(Some(vec![]));
+        };
+        if self.documents.get(uri).map(|doc| doc.version()) != Some(version) {
+            return Ok(None);
+        }
+        Ok(Some(manager.hints_within(range)))
+    }
+
+    /// Publishes the diagnostics of the document at `uri` once edits to it settle, and only when a
+    /// setting asks for some. They're published from here alone, when the document is opened or
+    /// edited, rather than with the hints clients request whenever they scroll.
+    async fn publish_diagnostics(&self, uri: &Url) {
+        let config = self.config();
+        if !config.enabled || !(config.check_arity || config.warn_single_table_argument) {
+            return;
+        }
+        let Some(version) = self.documents.get(uri).map(|doc| doc.version()) else {
+            return;
+        };
+        if !self.settle(uri).await {
+            return;
+        }
+        let Some(doc) = self
+            .documents
+            .get(uri)
+            .filter(|doc| doc.version() == version)
+        else {
+            // A later edit publishes its own diagnostics.
+            return;
+        };
+        if doc.text.byte_len() > config.max_file_size_bytes {
+            return;
+        }
+        drop(doc);
+        // The pass is shared with the hints the client requests for the same version.
+        let Ok(Some((built, manager))) = self.scope_manager(uri, config).await else {
+            return;
+        };
+        if built != version {
+            return;
+        }
+        self.client
+            .publish_diagnostics(uri.clone(), manager.diagnostics.clone(), Some(version))
+            .await;
+    }
+
+    async fn end_progress(&self, token: Option<ProgressToken>, message: Option<String>) {
+        let Some(token) = token else {
+            return;
+        };
+        self.client
+            .send_notification::<notification::Progress>(ProgressParams {
+                token,
+                value: ProgressParamsValue::WorkDone(WorkDoneProgress::End(WorkDoneProgressEnd {
+                    message,
+                })),
+            })
+            .await;
+    }
 }
 
 #[tower_lsp::async_trait]
 impl LanguageServer for Backend {
-    async fn initialize(&self, _: InitializeParams) -> Result<InitializeResult> {
+    async fn initialize(&self, params: InitializeParams) -> Result<InitializeResult> {
+        let work_done_progress = params
+            .capabilities
+            .window
+            .and_then(|window| window.work_done_progress)
+            .unwrap_or(false);
+        self.work_done_progress
+            .store(work_done_progress, Ordering::Relaxed);
+        let watch_files = params
+            .capabilities
+            .workspace
+            .and_then(|workspace| workspace.did_change_watched_files)
+            .and_then(|watched| watched.dynamic_registration)
+            .unwrap_or(false);
+        self.watch_files.store(watch_files, Ordering::Relaxed);
+
+        let roots = params
+            .workspace_folders
+            .into_iter()
+            .flatten()
+            .map(|folder| folder.uri)
+            .chain(params.root_uri)
+            .filter_map(|uri| uri.to_file_path().ok())
+            .collect::<Vec<_>>();
+        self.modules.set_roots(roots.clone());
+        self.index_roots(roots);
+
+        if let Some(options) = params.initialization_options {
+            self.set_config(options).await;
+        }
+
         Ok(InitializeResult {
             server_info: Some(ServerInfo {
                 name: env!("CARGO_PKG_NAME").to_owned(),
@@ -54,11 +647,21 @@ impl LanguageServer for Backend {
             }),
             capabilities: ServerCapabilities {
                 execute_command_provider: Some(ExecuteCommandOptions {
-                    commands: vec!["custom.notification".to_string()],
+                    commands: vec![
+                        "custom.notification".to_string(),
+                        "luahint.validateScopes".to_string(),
+                        "luahint.refresh".to_string(),
+                    ],
                     work_done_progress_options: Default::default(),
                 }),
-                text_document_sync: Some(TextDocumentSyncCapability::Kind(
-                    TextDocumentSyncKind::INCREMENTAL,
+                // Saves are reported so the workspace index follows the files on disk.
+                text_document_sync: Some(TextDocumentSyncCapability::Options(
+                    TextDocumentSyncOptions {
+                        open_close: Some(true),
+                        change: Some(TextDocumentSyncKind::INCREMENTAL),
+                        save: Some(TextDocumentSyncSaveOptions::Supported(true)),
+                        ..Default::default()
+                    },
                 )),
 
                 workspace: Some(WorkspaceServerCapabilities {
@@ -68,20 +671,67 @@ impl LanguageServer for Backend {
                     }),
                     file_operations: None,
                 }),
-                inlay_hint_provider: Some(OneOf::Left(true)),
+                // Hints are sent in a single response rather than as partial results: they all
+                // come out of one pass over the document, so none are ready before it ends. The
+                // pass reports progress instead.
+                inlay_hint_provider: Some(OneOf::Right(InlayHintServerCapabilities::Options(
+                    InlayHintOptions {
+                        work_done_progress_options: WorkDoneProgressOptions {
+                            work_done_progress: Some(true),
+                        },
+                        resolve_provider: None,
+                    },
+                ))),
+                hover_provider: Some(HoverProviderCapability::Simple(true)),
+                document_symbol_provider: Some(OneOf::Left(true)),
+                folding_range_provider: Some(FoldingRangeProviderCapability::Simple(true)),
+                document_highlight_provider: Some(OneOf::Left(true)),
+                definition_provider: Some(OneOf::Left(true)),
+                rename_provider: Some(OneOf::Left(true)),
+                signature_help_provider: Some(SignatureHelpOptions {
+                    trigger_characters: Some(vec!["(".to_string(), ",".to_string()]),
+                    retrigger_characters: None,
+                    work_done_progress_options: WorkDoneProgressOptions::default(),
+                }),
                 ..ServerCapabilities::default()
             },
             ..Default::default()
         })
     }
 
+    async fn initialized(&self, _: InitializedParams) {
+        if !self.watch_files.load(Ordering::Relaxed) {
+            return;
+        }
+        // Lua files changed outside the editor update the workspace index.
+        let options = DidChangeWatchedFilesRegistrationOptions {
+            watchers: vec![FileSystemWatcher {
+                glob_pattern: GlobPattern::String("**/*.lua".to_string()),
+                kind: None,
+            }],
+        };
+        let registration = Registration {
+            id: "luahint/watchedFiles".to_string(),
+            method: "workspace/didChangeWatchedFiles".to_string(),
+            register_options: serde_json::to_value(options).ok(),
+        };
+        if let Err(e) = self.client.register_capability(vec![registration]).await {
+            let message = format!("couldn't watch the workspace's Lua files: {e}");
+            self.client.log_message(MessageType::WARNING, message).await;
+        }
+    }
+
     async fn did_open(&self, params: DidOpenTextDocumentParams) {
         let TextDocumentItem {
             uri, version, text, ..
         } = params.text_document;
 
-        self.documents
-            .insert(uri.clone(), Doc::new(text, uri, version));
+        let doc = Doc::new(text, uri.clone(), version);
+        if let Ok(path) = uri.to_file_path() {
+            self.modules.set_open(path, version, doc.text.clone());
+        }
+        self.documents.insert(uri.clone(), doc);
+        self.publish_diagnostics(&uri).await;
     }
 
     async fn did_change(&self, params: DidChangeTextDocumentParams) {
@@ -89,30 +739,364 @@ impl LanguageServer for Backend {
         let Some(mut doc) = self.documents.get_mut(&uri) else {
 			return
 		};
-        let text = &mut doc.text;
-        params.content_changes.into_iter().for_each(|change| {
-            let Some(range) = change.range else {
-				return
-			};
-            let start_byte =
-                text.byte_of_line(range.start.line as usize) + range.start.character as usize;
-            let end_byte =
-                text.byte_of_line(range.end.line as usize) + range.end.character as usize;
-            text.replace(start_byte..end_byte, change.text);
-        });
+        let edited_from = apply_changes(&mut doc.text, params.content_changes);
         doc.version
             .swap(params.text_document.version, Ordering::Relaxed);
+        doc.edited_at = Some(Instant::now());
+        // Text before the earliest edit is unchanged, so its statements can be kept on reparse.
+        if let Some(parsed) = doc.parsed.write().unwrap().as_mut() {
+            parsed.edited_from = Some(
+                parsed
+                    .edited_from
+                    .map_or(edited_from, |from| from.min(edited_from)),
+            );
+        }
+        // Files requiring this one see its unsaved text. Cloning the rope is cheap, it's only
+        // converted to a string if one of them is analyzed.
+        if let Ok(path) = uri.to_file_path() {
+            self.modules
+                .set_open(path, params.text_document.version, doc.text.clone());
+        }
+        drop(doc);
+        self.publish_diagnostics(&uri).await;
+    }
+
+    async fn did_close(&self, params: DidCloseTextDocumentParams) {
+        let uri = params.text_document.uri;
+        self.documents.remove(&uri);
+        if let Ok(path) = uri.to_file_path() {
+            self.modules.close(&path);
+        }
+        // Nothing updates the warnings of a closed document, so they'd stay until it's reopened.
+        self.client.publish_diagnostics(uri, vec![], None).await;
+    }
+
+    async fn did_save(&self, params: DidSaveTextDocumentParams) {
+        self.reindex(&params.text_document.uri);
+    }
+
+    async fn did_change_watched_files(&self, params: DidChangeWatchedFilesParams) {
+        // Files changed outside the editor, including created and deleted ones.
+        for change in params.changes {
+            self.reindex(&change.uri);
+        }
+    }
+
+    async fn did_change_workspace_folders(&self, params: DidChangeWorkspaceFoldersParams) {
+        let paths = |folders: Vec<WorkspaceFolder>| {
+            folders
+                .into_iter()
+                .filter_map(|folder| folder.uri.to_file_path().ok())
+                .collect::<Vec<_>>()
+        };
+        let removed = paths(params.event.removed);
+        let added = paths(params.event.added);
+        let mut roots = self.modules.roots();
+        roots.retain(|root| !removed.contains(root));
+        roots.extend(added.iter().cloned());
+        self.modules.set_roots(roots);
+        for root in &removed {
+            self.index.remove_root(root);
+        }
+        self.index_roots(added);
+    }
+
+    async fn did_change_configuration(&self, params: DidChangeConfigurationParams) {
+        // Clients send either the whole settings object or just the `luahint` section.
+        let settings = match params.settings {
+            serde_json::Value::Object(mut settings) if settings.contains_key("luahint") => {
+                settings.remove("luahint").unwrap_or_default()
+            }
+            settings => settings,
+        };
+        self.set_config(settings).await;
+        // Hints already shown were computed with the old settings.
+        let _ = self.client.inlay_hint_refresh().await;
     }
 
     async fn inlay_hint(&self, params: InlayHintParams) -> Result<Option<Vec<InlayHint>>> {
+        let config = self.config();
+        if !config.enabled {
+            return Ok(Some(vec![]));
+        }
+        let uri = params.text_document.uri;
+        let Some(doc) = self.documents.get(&uri) else {
+            return Ok(None);
+        };
+        if doc.text.byte_len() > config.max_file_size_bytes {
+            let notify = !doc.size_notified.swap(true, Ordering::Relaxed);
+            drop(doc);
+            if notify {
+                let message = format!(
+                    "{uri} is larger than maxFileSizeBytes ({} bytes), skipping inlay hints",
+                    config.max_file_size_bytes
+                );
+                self.client.log_message(MessageType::INFO, message).await;
+            }
+            return Ok(Some(vec![]));
+        }
+        let large = doc.text.line_len() >= PROGRESS_LINE_THRESHOLD;
+        let tick = self.hint_clock.fetch_add(1, Ordering::Relaxed) + 1;
+        doc.hinted.store(tick, Ordering::Relaxed);
+        drop(doc);
+
+        if !self.settle(&uri).await {
+            return Ok(None);
+        }
+        let progress = self
+            .begin_progress(
+                params.work_done_progress_params.work_done_token,
+                large,
+                "Computing inlay hints",
+            )
+            .await;
+        let mut result = Ok(None);
+        for _ in 0..MAX_HINT_ATTEMPTS {
+            result = self.compute_hints(&uri, params.range, config.clone()).await;
+            // `None` means the document changed while the hints were computed, so their
+            // positions no longer match it. Compute them again for the latest text.
+            if !matches!(result, Ok(None)) {
+                break;
+            }
+        }
+        let message = result
+            .as_ref()
+            .ok()
+            .and_then(Option::as_ref)
+            .map(|hints| format!("{} hints", hints.len()));
+        self.end_progress(progress, message).await;
+        // If the document never held still, the client asks again for the version it ends at.
+        result
+    }
+
+    async fn hover(&self, params: HoverParams) -> Result<Option<Hover>> {
+        let TextDocumentPositionParams {
+            text_document,
+            position,
+        } = params.text_document_position_params;
+        let config = self.config();
+        let Some(doc) = self.documents.get(&text_document.uri) else {
+            return Ok(None);
+        };
+        if doc.text.byte_len() > config.max_file_size_bytes {
+            return Ok(None);
+        }
+        let offset = position_to_byte(&doc.text, position);
+        let version = doc.version();
+        drop(doc);
+        let Some(manager) = self.manager_at(&text_document.uri, version, config).await? else {
+            return Ok(None);
+        };
+        Ok(manager.signature_at(offset).map(|signature| Hover {
+            contents: HoverContents::Markup(MarkupContent {
+                kind: MarkupKind::Markdown,
+                value: format!("```lua\n{signature}\n```"),
+            }),
+            range: None,
+        }))
+    }
+
+    async fn signature_help(&self, params: SignatureHelpParams) -> Result<Option<SignatureHelp>> {
+        let TextDocumentPositionParams {
+            text_document,
+            position,
+        } = params.text_document_position_params;
+        let config = self.config();
+        let Some(doc) = self.documents.get(&text_document.uri) else {
+            return Ok(None);
+        };
+        if doc.text.byte_len() > config.max_file_size_bytes {
+            return Ok(None);
+        }
+        let offset = position_to_byte(&doc.text, position);
+        let version = doc.version();
+        drop(doc);
+        let Some(manager) = self.manager_at(&text_document.uri, version, config).await? else {
+            return Ok(None);
+        };
+        Ok(manager.call_at(offset).map(|call| {
+            // Parameters are labelled by their offsets in the signature, as a name can also occur
+            // within the function's name. Signatures are ASCII, so bytes are UTF-16 code units.
+            let mut start = call.signature.find('(').map_or(0, |open| open + 1) as u32;
+            let parameters = call
+                .params
+                .iter()
+                .map(|param| {
+                    let end = start + param.len() as u32;
+                    let label = ParameterLabel::LabelOffsets([start, end]);
+                    start = end + ", ".len() as u32;
+                    ParameterInformation {
+                        label,
+                        documentation: None,
+                    }
+                })
+                .collect();
+            SignatureHelp {
+                signatures: vec![SignatureInformation {
+                    label: call.signature.clone(),
+                    documentation: None,
+                    parameters: Some(parameters),
+                    active_parameter: None,
+                }],
+                active_signature: Some(0),
+                active_parameter: Some(call.active_parameter(offset) as u32),
+            }
+        }))
+    }
+
+    async fn goto_definition(
+        &self,
+        params: GotoDefinitionParams,
+    ) -> Result<Option<GotoDefinitionResponse>> {
+        let TextDocumentPositionParams {
+            text_document,
+            position,
+        } = params.text_document_position_params;
+        let config = self.config();
+        let Some(doc) = self.documents.get(&text_document.uri) else {
+            return Ok(None);
+        };
+        if doc.text.byte_len() > config.max_file_size_bytes {
+            return Ok(None);
+        }
+        let offset = position_to_byte(&doc.text, position);
+        let version = doc.version();
+        drop(doc);
+        let Some(manager) = self.manager_at(&text_document.uri, version, config).await? else {
+            return Ok(None);
+        };
+        if let Some(range) = manager.definition_at(offset) {
+            return Ok(Some(GotoDefinitionResponse::Scalar(Location {
+                uri: text_document.uri,
+                range,
+            })));
+        }
+        // Functions of required modules are defined in the file the workspace index found them in.
+        let definition = manager
+            .module_export_at(offset)
+            .and_then(|(module, export)| self.index.function(module, export))
+            .and_then(|(path, function)| Some((Url::from_file_path(path).ok()?, function.range)));
+        Ok(definition.map(|(uri, range)| GotoDefinitionResponse::Scalar(Location { uri, range })))
+    }
+
+    async fn document_highlight(
+        &self,
+        params: DocumentHighlightParams,
+    ) -> Result<Option<Vec<DocumentHighlight>>> {
+        let TextDocumentPositionParams {
+            text_document,
+            position,
+        } = params.text_document_position_params;
+        let config = self.config();
+        let Some(doc) = self.documents.get(&text_document.uri) else {
+            return Ok(None);
+        };
+        if doc.text.byte_len() > config.max_file_size_bytes {
+            return Ok(None);
+        }
+        let offset = position_to_byte(&doc.text, position);
+        let version = doc.version();
+        drop(doc);
+        let Some(manager) = self.manager_at(&text_document.uri, version, config).await? else {
+            return Ok(None);
+        };
+
+        let highlights = manager
+            .usages_at(offset)
+            .into_iter()
+            .map(|usage| DocumentHighlight {
+                range: usage.range,
+                kind: Some(if usage.write {
+                    DocumentHighlightKind::WRITE
+                } else {
+                    DocumentHighlightKind::READ
+                }),
+            })
+            .collect::<Vec<_>>();
+        Ok((!highlights.is_empty()).then_some(highlights))
+    }
+
+    async fn rename(&self, params: RenameParams) -> Result<Option<WorkspaceEdit>> {
+        let TextDocumentPositionParams {
+            text_document,
+            position,
+        } = params.text_document_position;
+        if !is_identifier(&params.new_name) {
+            return Err(jsonrpc::Error::invalid_params(format!(
+                "`{}` is not a valid Lua name",
+                params.new_name
+            )));
+        }
+        let config = self.config();
+        let Some(doc) = self.documents.get(&text_document.uri) else {
+            return Ok(None);
+        };
+        if doc.text.byte_len() > config.max_file_size_bytes {
+            return Ok(None);
+        }
+        let offset = position_to_byte(&doc.text, position);
+        let version = doc.version();
+        drop(doc);
+        let Some(manager) = self.manager_at(&text_document.uri, version, config).await? else {
+            return Ok(None);
+        };
+
+        // Only locals are tracked, so globals and fields are never renamed.
+        let edits = manager
+            .usages_at(offset)
+            .into_iter()
+            .map(|usage| TextEdit {
+                range: usage.range,
+                new_text: params.new_name.clone(),
+            })
+            .collect::<Vec<_>>();
+        if edits.is_empty() {
+            return Ok(None);
+        }
+        Ok(Some(WorkspaceEdit {
+            changes: Some([(text_document.uri, edits)].into_iter().collect()),
+            ..Default::default()
+        }))
+    }
+
+    async fn document_symbol(
+        &self,
+        params: DocumentSymbolParams,
+    ) -> Result<Option<DocumentSymbolResponse>> {
         let Some(doc) = self.documents.get(&params.text_document.uri) else {
-			return Ok(None)
-		};
+            return Ok(None);
+        };
+        if doc.text.byte_len() > self.config().max_file_size_bytes {
+            return Ok(None);
+        }
         let text = doc.text.to_string();
-        let ast = full_moon::parse(&text).map_err(|_| jsonrpc::Error::internal_error())?;
-        let hints = ScopeManager::new(ast).hints;
+        let reparse = doc.reparse();
+        self.count_parse(&reparse);
+        drop(doc);
+
+        let symbols = tokio::task::spawn_blocking(move || {
+            let ast = reparse.parse(&text)?;
+            Some(document_symbols(&ast))
+        })
+        .await
+        .map_err(|_| jsonrpc::Error::internal_error())?;
+        Ok(symbols.map(DocumentSymbolResponse::Nested))
+    }
 
-        Ok(Some(hints))
+    async fn folding_range(&self, params: FoldingRangeParams) -> Result<Option<Vec<FoldingRange>>> {
+        let config = self.config();
+        let uri = params.text_document.uri;
+        let Some(doc) = self.documents.get(&uri) else {
+            return Ok(None);
+        };
+        if doc.text.byte_len() > config.max_file_size_bytes {
+            return Ok(None);
+        }
+        drop(doc);
+        let Some((_, manager)) = self.scope_manager(&uri, config).await? else {
+            return Ok(None);
+        };
+        Ok(Some(manager.folding_ranges().to_vec()))
     }
 
     async fn inlay_hint_resolve(&self, params: InlayHint) -> Result<InlayHint> {
@@ -120,13 +1104,40 @@ impl LanguageServer for Backend {
     }
 
     async fn shutdown(&self) -> Result<()> {
+        // Work still waiting for edits to settle is dropped, and so is workspace indexing.
+        self.shut_down.store(true, Ordering::Relaxed);
+        self.shutting_down.notify_waiters();
+        self.index.cancel();
+        // The client would otherwise keep showing the warnings of the last pass.
+        let uris = self
+            .documents
+            .iter()
+            .map(|doc| doc.key().clone())
+            .collect::<Vec<_>>();
+        for uri in uris {
+            self.client.publish_diagnostics(uri, vec![], None).await;
+        }
+        // No more requests are served after shutdown, so drop the per-session state.
+        self.documents.clear();
+        self.modules.clear();
         Ok(())
     }
 
     async fn execute_command(
         &self,
-        _params: ExecuteCommandParams,
+        params: ExecuteCommandParams,
     ) -> Result<Option<serde_json::Value>> {
-        Ok(None)
+        match params.command.as_str() {
+            "luahint.validateScopes" => {
+                let problems = self.validate_scopes(params.arguments).await?;
+                Ok(Some(serde_json::json!(problems)))
+            }
+            // Makes the client pull hints again, e.g. after a setting that affects them changed.
+            "luahint.refresh" => {
+                self.client.inlay_hint_refresh().await?;
+                Ok(None)
+            }
+            _ => Ok(None),
+        }
     }
 }
@@ -1,16 +1,22 @@
-use lsp::Backend;
+use luahint::config::Config;
+use luahint::lsp::Backend;
 use tower_lsp::jsonrpc::Result;
 use tower_lsp::{LspService, Server};
 
-mod lsp;
-mod scope;
-mod visitor;
-
 #[tokio::main]
 async fn main() -> Result<()> {
+    // Lets editor extensions bundle a schema of the settings for validation and completion.
+    if std::env::args().any(|arg| arg == "--print-config-schema") {
+        let schema = schemars::schema_for!(Config);
+        println!("{}", serde_json::to_string_pretty(&schema).unwrap());
+        return Ok(());
+    }
+
     let (stdin, stdout) = (tokio::io::stdin(), tokio::io::stdout());
 
-    let (service, socket) = LspService::new(Backend::new);
+    let (service, socket) = LspService::build(Backend::new)
+        .custom_method("luahint/hints", Backend::hints)
+        .finish();
     Server::new(stdin, stdout, socket).serve(service).await;
     Ok(())
 }
@@ -0,0 +1,281 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Arc, RwLock};
+use std::time::SystemTime;
+
+use crop::Rope;
+use dashmap::DashMap;
+use full_moon::ast::{Expression, LastStmt, TableConstructor, Value};
+
+use crate::config::Config;
+use crate::scope::{ScopeId, ScopeManager};
+use crate::visitor::Fields;
+
+/// The values a module returns, keyed by their dotted path within the returned table.
+pub type Exports = HashMap<String, Value>;
+
+/// What a module was read from, to tell whether its cached exports are still current.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Stamp {
+    /// The file, as last modified at this time.
+    Disk(SystemTime),
+    /// The open document, at this version.
+    Open(i32),
+}
+
+#[derive(Debug)]
+struct CacheEntry {
+    stamp: Stamp,
+    exports: Option<Arc<Exports>>,
+    /// The tick of the last lookup, for least recently used eviction.
+    last_used: AtomicU64,
+}
+
+/// Resolves `require`d module names to their exports by loading them from the workspace. Modules
+/// open in the editor are read from their current text rather than from disk, so unsaved edits
+/// are seen by the files requiring them.
+#[derive(Debug)]
+pub struct Modules {
+    roots: RwLock<Vec<PathBuf>>,
+    cache: DashMap<PathBuf, CacheEntry>,
+    /// The version and text of the documents open in the editor, by path. The text is only
+    /// converted to a string when a file requiring the module is analyzed.
+    open: DashMap<PathBuf, (i32, Rope)>,
+    /// Bumped whenever any module may have changed, as when the roots change.
+    generation: AtomicU64,
+    /// The revision of each module file opened, edited or closed in the editor, so analyses of the
+    /// files requiring it can be told apart from current ones. Taken from `next_revision`.
+    revisions: DashMap<PathBuf, u64>,
+    next_revision: AtomicU64,
+    capacity: AtomicUsize,
+    tick: AtomicU64,
+}
+
+impl Default for Modules {
+    fn default() -> Self {
+        Self {
+            roots: RwLock::default(),
+            cache: DashMap::new(),
+            open: DashMap::new(),
+            generation: AtomicU64::new(0),
+            revisions: DashMap::new(),
+            next_revision: AtomicU64::new(0),
+            capacity: AtomicUsize::new(Config::default().max_cached_modules),
+            tick: AtomicU64::new(0),
+        }
+    }
+}
+
+impl Modules {
+    /// Sets how many modules are kept cached, evicting any over the limit.
+    pub fn set_capacity(&self, capacity: usize) {
+        self.capacity.store(capacity, Ordering::Relaxed);
+        self.evict();
+    }
+
+    pub fn roots(&self) -> Vec<PathBuf> {
+        self.roots.read().unwrap().clone()
+    }
+
+    pub fn set_roots(&self, roots: Vec<PathBuf>) {
+        *self.roots.write().unwrap() = roots;
+        self.cache.clear();
+        self.generation.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn clear(&self) {
+        self.cache.clear();
+        self.open.clear();
+        self.generation.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Reads the module at `path` from `text`, the content of an open document at `version`,
+    /// instead of from disk. Only the files requiring the module are affected.
+    pub fn set_open(&self, path: PathBuf, version: i32, text: Rope) {
+        self.bump_revision(path.clone());
+        self.open.insert(path, (version, text));
+    }
+
+    /// Goes back to reading the module at `path` from disk once its document is closed.
+    pub fn close(&self, path: &Path) {
+        self.open.remove(path);
+        self.bump_revision(path.to_path_buf());
+    }
+
+    fn bump_revision(&self, path: PathBuf) {
+        let revision = self.next_revision.fetch_add(1, Ordering::Relaxed) + 1;
+        self.revisions.insert(path, revision);
+    }
+
+    /// A counter that changes whenever every module may have, as when the roots change.
+    pub fn generation(&self) -> u64 {
+        self.generation.load(Ordering::Relaxed)
+    }
+
+    /// A counter that changes whenever the module `name` is opened, edited or closed in the
+    /// editor. Modules that were never open, or can't be found, are at revision 0.
+    pub fn revision(&self, name: &str) -> u64 {
+        self.find(name)
+            .and_then(|path| self.revisions.get(&path).map(|revision| *revision))
+            .unwrap_or(0)
+    }
+
+    /// Finds the file of a module, looking for both `name.lua` and `name/init.lua` in each root
+    /// and in its `lua` directory, as Neovim's runtime does.
+    pub fn find(&self, name: &str) -> Option<PathBuf> {
+        let relative = name.replace('.', "/");
+        self.roots.read().unwrap().iter().find_map(|root| {
+            [root.clone(), root.join("lua")]
+                .into_iter()
+                .flat_map(|dir| {
+                    [
+                        dir.join(format!("{relative}.lua")),
+                        dir.join(&relative).join("init.lua"),
+                    ]
+                })
+                .find(|path| self.open.contains_key(path) || path.is_file())
+        })
+    }
+
+    /// The exports of the module `name`, or `None` if it can't be found or parsed.
+    pub fn exports(self: &Arc<Self>, name: &str) -> Option<Arc<Exports>> {
+        let path = self.find(name)?;
+        let open = self.open.get(&path).map(|open| open.clone());
+        let stamp = match &open {
+            Some((version, _)) => Stamp::Open(*version),
+            None => Stamp::Disk(
+                std::fs::metadata(&path)
+                    .and_then(|metadata| metadata.modified())
+                    .ok()?,
+            ),
+        };
+        let tick = self.tick.fetch_add(1, Ordering::Relaxed);
+        if let Some(entry) = self.cache.get(&path) {
+            if entry.stamp == stamp {
+                entry.last_used.store(tick, Ordering::Relaxed);
+                return entry.exports.clone();
+            }
+        }
+
+        // Record a miss first so that a require cycle resolves to nothing instead of recursing.
+        self.insert(path.clone(), stamp, None, tick);
+        let text = match open {
+            Some((_, text)) => Some(text.to_string()),
+            None => std::fs::read_to_string(&path).ok(),
+        };
+        let exports = text
+            .and_then(|text| full_moon::parse(&text).ok())
+            .map(|ast| {
+                let manager = ScopeManager::new(ast, Arc::default(), self.clone()).build();
+                Arc::new(collect_exports(&manager))
+            });
+        self.insert(path, stamp, exports.clone(), tick);
+        exports
+    }
+
+    fn insert(&self, path: PathBuf, stamp: Stamp, exports: Option<Arc<Exports>>, tick: u64) {
+        self.cache.insert(
+            path,
+            CacheEntry {
+                stamp,
+                exports,
+                last_used: AtomicU64::new(tick),
+            },
+        );
+        self.evict();
+    }
+
+    /// Drops the least recently used entries until the cache is within its capacity.
+    fn evict(&self) {
+        let capacity = self.capacity.load(Ordering::Relaxed);
+        while self.cache.len() > capacity {
+            let oldest = self
+                .cache
+                .iter()
+                .min_by_key(|entry| entry.last_used.load(Ordering::Relaxed))
+                .map(|entry| entry.key().clone());
+            match oldest {
+                Some(path) => self.cache.remove(&path),
+                None => break,
+            };
+        }
+    }
+}
+
+/// Collects the values returned by a module's final `return` statement.
+pub fn collect_exports(manager: &ScopeManager) -> Exports {
+    let mut exports = Exports::new();
+    let block = manager.ast.nodes();
+    let (Some(LastStmt::Return(ret)), Some(scope)) = (block.last_stmt(), manager.chunk) else {
+        return exports;
+    };
+    let Some(Expression::Value { value, .. }) = ret.returns().iter().next() else {
+        return exports;
+    };
+    match value.as_ref() {
+        Value::Var(full_moon::ast::Var::Name(name)) => {
+            let name = name.token().to_string();
+            let Some((owner, var)) = manager.find_var_from(scope, &name) else {
+                return exports;
+            };
+            if let Some(Value::TableConstructor(table)) = manager.var_value(owner, var) {
+                table_exports(manager, scope, table, "", &mut exports);
+            }
+            // Fields assigned or declared after the table was created are stored as dotted names,
+            // and methods as `name:method`. Methods are exported as `:method`.
+            let prefix = format!("{name}.");
+            let methods = format!("{name}:");
+            for (key, var) in manager.scopes[owner].var_names.iter() {
+                let Some(value) = manager.var_value(owner, *var) else {
+                    continue;
+                };
+                if let Some(field) = key.strip_prefix(&prefix) {
+                    exports.insert(field.to_string(), value.clone());
+                } else if let Some(method) = key.strip_prefix(&methods) {
+                    exports.insert(format!(":{method}"), value.clone());
+                }
+            }
+        }
+        Value::TableConstructor(table) => table_exports(manager, scope, table, "", &mut exports),
+        _ => {}
+    }
+    exports
+}
+
+/// Adds the functions and tables in a table constructor to `exports`, resolving names against
+/// `scope`.
+fn table_exports(
+    manager: &ScopeManager,
+    scope: ScopeId,
+    table: &TableConstructor,
+    prefix: &str,
+    exports: &mut Exports,
+) {
+    for (key, value) in table.named_fields() {
+        let Expression::Value { value, .. } = value else {
+            continue;
+        };
+        let value = match value.as_ref() {
+            Value::Var(full_moon::ast::Var::Name(name)) => {
+                let Some(value) = manager
+                    .find_var_from(scope, &name.token().to_string())
+                    .and_then(|(owner, var)| manager.var_value(owner, var))
+                else {
+                    continue;
+                };
+                value
+            }
+            value => value,
+        };
+        let path = format!("{prefix}{key}");
+        match value {
+            Value::Function(_) => {}
+            Value::TableConstructor(table) => {
+                table_exports(manager, scope, table, &format!("{path}."), exports)
+            }
+            _ => continue,
+        }
+        exports.insert(path, value.clone());
+    }
+}
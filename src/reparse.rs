@@ -0,0 +1,274 @@
+use std::sync::Arc;
+
+use full_moon::ast::{Ast, AstError, Block, Stmt};
+use full_moon::node::Node;
+use full_moon::tokenizer::TokenReference;
+
+/// Top-level statements with their optional trailing semicolons.
+type Stmts = Vec<(Stmt, Option<TokenReference>)>;
+
+/// How many segments back from a syntax error recovery looks at once for the start of a block
+/// that isn't closed, as when a function's body isn't indented.
+const MAX_REGION_SEGMENTS: usize = 8;
+
+/// What is known about the parse of a document's current text.
+#[derive(Debug)]
+pub enum Reparse {
+    /// The cached parse is of the current text.
+    Current(Arc<Ast>),
+    /// The text was edited since the cached parse, but everything before the byte offset is
+    /// unchanged.
+    Edited(Arc<Ast>, usize),
+    /// Nothing usable is cached.
+    Full,
+}
+
+impl Reparse {
+    /// Parses `text`, reusing as much of the cached parse as is still valid.
+    ///
+    /// When the text doesn't parse, as while typing, the statements that do parse are returned on
+    /// their own so that they still get hints, see [`recover`].
+    pub fn parse(self, text: &str) -> Option<Arc<Ast>> {
+        let (old, edited_from) = match self {
+            Reparse::Current(ast) => return Some(ast),
+            Reparse::Edited(ast, edited_from) => (ast, edited_from),
+            Reparse::Full => return parse_full(text).map(Arc::new),
+        };
+        let Some((mut kept, boundary, line)) = unchanged(&old, text, edited_from) else {
+            return parse_full(text).map(Arc::new);
+        };
+
+        // Every statement after the edit is reparsed, on its own, then moved into place.
+        let ast = match parse_at(&text[boundary..], boundary, line) {
+            Ok(ast) => {
+                kept.extend(ast.nodes().stmts_with_semicolon().cloned());
+                let block = ast.nodes().clone().with_stmts(kept);
+                ast.with_nodes(block)
+            }
+            Err(error_at) => {
+                let recovered = recover(text, boundary, line, error_at);
+                kept.extend(recovered.stmts_with_semicolon().cloned());
+                (*old).clone().with_nodes(recovered.with_stmts(kept))
+            }
+        };
+        Some(Arc::new(ast))
+    }
+}
+
+/// Parses the whole of `text`, recovering what it can when it doesn't parse.
+fn parse_full(text: &str) -> Option<Ast> {
+    match full_moon::parse(text) {
+        Ok(ast) => Some(ast),
+        Err(error) => {
+            let recovered = recover(text, 0, 1, error_offset(&error, text));
+            Some(full_moon::parse("").ok()?.with_nodes(recovered))
+        }
+    }
+}
+
+/// The byte offset into `source` at which parsing it failed with `error`.
+fn error_offset(error: &full_moon::Error, source: &str) -> usize {
+    match error {
+        full_moon::Error::AstError(AstError::UnexpectedToken { token, .. }) => {
+            token.start_position().bytes()
+        }
+        full_moon::Error::TokenizerError(error) => error.position().bytes(),
+        _ => source.len(),
+    }
+}
+
+/// Parses `source`, the text of a document from byte `offset` on, which is the start of the
+/// 1-based `line`. Only `source` is tokenized, and its tokens are then moved to their positions in
+/// the document. On failure, returns the byte offset in the document at which parsing failed.
+fn parse_at(source: &str, offset: usize, line: usize) -> Result<Ast, usize> {
+    let ast = full_moon::parse(source).map_err(|error| offset + error_offset(&error, source))?;
+    if offset == 0 {
+        return Ok(ast);
+    }
+    // full_moon has no way to set a token's position, so they're shifted in the serialized tree.
+    let mut value = serde_json::to_value(&ast).map_err(|_| offset)?;
+    shift_positions(&mut value, offset, line - 1);
+    serde_json::from_value(value).map_err(|_| offset)
+}
+
+/// Moves the serialized token positions within `value` forward by `bytes` bytes and `lines` lines.
+/// Columns are left alone, as shifted text always starts at the beginning of a line.
+fn shift_positions(value: &mut serde_json::Value, bytes: usize, lines: usize) {
+    match value {
+        serde_json::Value::Object(fields) => {
+            let is_position = fields.len() == 3
+                && ["bytes", "character", "line"]
+                    .iter()
+                    .all(|key| fields.get(*key).is_some_and(serde_json::Value::is_u64));
+            if !is_position {
+                fields
+                    .values_mut()
+                    .for_each(|value| shift_positions(value, bytes, lines));
+                return;
+            }
+            for (key, by) in [("bytes", bytes), ("line", lines)] {
+                if let Some(n) = fields.get(key).and_then(serde_json::Value::as_u64) {
+                    fields.insert(key.to_string(), (n + by as u64).into());
+                }
+            }
+        }
+        serde_json::Value::Array(items) => items
+            .iter_mut()
+            .for_each(|value| shift_positions(value, bytes, lines)),
+        _ => {}
+    }
+}
+
+/// The statements of `text` from byte `from`, the start of the 1-based `line`, on, for text that
+/// doesn't parse, with parsing having failed at byte `error_at`.
+///
+/// The text is split into segments at lines that likely start a top-level statement. Only the
+/// region around each error is parsed segment by segment: the clean text before the segment with
+/// the error is parsed once, as is the text after it. When the text before doesn't parse, a block
+/// opened there is never closed, and the region reaches back, at most [`MAX_REGION_SEGMENTS`]
+/// segments at a time, to where the text before does parse. Of the segments in the region, those
+/// that parse on their own are kept, and a function whose body doesn't parse is kept with an empty
+/// body, so that calls to it still get hints. Anything else in the region is skipped.
+fn recover(text: &str, from: usize, line: usize, mut error_at: usize) -> Block {
+    let starts = statement_starts(text, from, line);
+    let end_of = |i: usize| starts.get(i).map_or(text.len(), |&(start, _)| start);
+    let mut stmts = vec![];
+    let mut last_stmt = None;
+    let mut i = 0;
+    loop {
+        let (start, line) = starts[i];
+        // The segment with the error.
+        let error = starts
+            .partition_point(|&(start, _)| start <= error_at)
+            .saturating_sub(1)
+            .max(i);
+
+        // The clean text before the region with the error, which starts earlier than the segment
+        // with the error while the text before leaves a block open.
+        let lowest = error.saturating_sub(MAX_REGION_SEGMENTS).max(i);
+        let mut region = error;
+        while region > i {
+            match parse_at(&text[start..end_of(region)], start, line) {
+                Ok(ast) => {
+                    stmts.extend(ast.nodes().stmts_with_semicolon().cloned());
+                    last_stmt = ast.nodes().last_stmt_with_semicolon().cloned();
+                    break;
+                }
+                Err(error_at) if region == lowest => {
+                    let block = recover(&text[..end_of(region)], start, line, error_at);
+                    stmts.extend(block.stmts_with_semicolon().cloned());
+                    last_stmt = block.last_stmt_with_semicolon().cloned();
+                    break;
+                }
+                Err(_) => region -= 1,
+            }
+        }
+
+        for index in region..=error {
+            let (segment_start, line) = starts[index];
+            let segment = &text[segment_start..end_of(index + 1)];
+            let ast = parse_at(segment, segment_start, line).ok().or_else(|| {
+                let header = function_header(segment)?;
+                parse_at(&header, segment_start, line).ok()
+            });
+            if let Some(ast) = ast {
+                stmts.extend(ast.nodes().stmts_with_semicolon().cloned());
+                last_stmt = ast.nodes().last_stmt_with_semicolon().cloned();
+            }
+        }
+
+        // Past the segment with the error, the rest of the text usually parses on its own.
+        i = error + 1;
+        let Some(&(start, line)) = starts.get(i) else {
+            break;
+        };
+        match parse_at(&text[start..], start, line) {
+            Ok(ast) => {
+                stmts.extend(ast.nodes().stmts_with_semicolon().cloned());
+                last_stmt = ast.nodes().last_stmt_with_semicolon().cloned();
+                break;
+            }
+            Err(at) => error_at = at,
+        }
+    }
+    Block::new().with_stmts(stmts).with_last_stmt(last_stmt)
+}
+
+/// The byte offsets and 1-based numbers of the lines of `text` from byte `from`, the start of
+/// `first_line`, on that likely start a top-level statement: lines that aren't indented and don't
+/// close a block.
+fn statement_starts(text: &str, from: usize, first_line: usize) -> Vec<(usize, usize)> {
+    let mut starts = vec![(from, first_line)];
+    let mut offset = from;
+    for (number, line) in (first_line..).zip(text[from..].split_inclusive('\n')) {
+        let start = offset;
+        offset += line.len();
+        if start == from || line.starts_with(char::is_whitespace) {
+            continue;
+        }
+        let word = line
+            .split(|c: char| !c.is_alphanumeric() && c != '_')
+            .next()
+            .unwrap_or_default();
+        let closes = matches!(word, "end" | "else" | "elseif" | "until")
+            || line.starts_with(|c| matches!(c, '}' | ')' | ']'));
+        if !closes {
+            starts.push((start, number));
+        }
+    }
+    starts
+}
+
+/// The first function of `source` up to the end of its parameter list, closed with an empty body,
+/// as in `local function f(a, b) end`.
+fn function_header(source: &str) -> Option<String> {
+    let keyword = source.find("function")?;
+    let open = keyword + source[keyword..].find('(')?;
+    let mut depth = 0;
+    for (i, c) in source[open..].char_indices() {
+        match c {
+            '(' => depth += 1,
+            ')' => {
+                depth -= 1;
+                if depth == 0 {
+                    return Some(format!("{} end", &source[..=open + i]));
+                }
+            }
+            _ => {}
+        }
+    }
+    None
+}
+
+/// The top-level statements of `old` that end before `edited_from`, the byte offset they end at,
+/// and the 1-based line that starts there. Returns `None` when the statements can't be cleanly
+/// separated from the rest, e.g. when the edit is inside the first statement or the last kept one
+/// shares its line with the next one, in which case the whole text must be parsed.
+fn unchanged(old: &Ast, text: &str, edited_from: usize) -> Option<(Stmts, usize, usize)> {
+    let mut kept = vec![];
+    let mut boundary = 0;
+    let mut line = 1;
+    for (stmt, semicolon) in old.nodes().stmts_with_semicolon() {
+        // A statement ends with the trailing trivia of its last token, which runs to the end of
+        // its line.
+        let last = match semicolon {
+            Some(semicolon) => semicolon,
+            None => stmt.tokens().last()?,
+        };
+        let end = match last.trailing_trivia().last() {
+            Some(trivia) => trivia.end_position().bytes(),
+            None => last.token().end_position().bytes(),
+        };
+        if end > edited_from {
+            break;
+        }
+        kept.push((stmt.clone(), semicolon.clone()));
+        boundary = end;
+        // The trailing trivia ends the last token's line, so the next statement starts on the next.
+        line = last.token().end_position().line() + 1;
+    }
+    if boundary == 0 || text.as_bytes().get(boundary - 1) != Some(&b'\n') {
+        return None;
+    }
+    Some((kept, boundary, line))
+}
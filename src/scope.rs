@@ -1,14 +1,22 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, Mutex};
 
 use full_moon::{
     ast::{Ast, Value},
     node::Node,
+    tokenizer::Position,
     visitors::Visitor,
 };
 use linked_hash_map::LinkedHashMap;
-use lsp_types::InlayHint;
+use lsp_types::{Diagnostic, FoldingRange, InlayHint, Range};
 use slotmap::{new_key_type, SlotMap};
 
+use crate::annotations::Annotations;
+use crate::config::Config;
+use crate::definitions;
+use crate::modules::{collect_exports, Exports, Modules};
+use crate::visitor::{lsp_position, parameters, slot_name};
+
 new_key_type! {
     pub struct ScopeId;
     pub struct VarId;
@@ -19,6 +27,71 @@ new_key_type! {
 pub enum Var {
     Local(ValueId),
     Reference(ScopeId, VarId),
+    /// A function parameter. Its value is unknown, but it still shadows outer variables.
+    Parameter,
+    /// A local whose value is unknown, like `local x` or `local x = a + b`. Like a parameter, it
+    /// still shadows outer variables.
+    Unknown,
+}
+
+/// A single scope operation performed during the visitor pass, recorded when the `trace` feature is
+/// enabled.
+#[cfg(feature = "trace")]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TraceEvent {
+    /// A new scope was created and pushed onto the stack.
+    OpenScope {
+        scope: ScopeId,
+        name: Option<String>,
+    },
+    /// The top of the stack was popped.
+    CloseScope { scope: Option<ScopeId> },
+    /// A local was allocated in a scope.
+    AllocLocal { scope: ScopeId, name: String },
+    /// A hint was emitted.
+    Hint {
+        label: String,
+        line: u32,
+        character: u32,
+    },
+}
+
+/// Records a [`TraceEvent`] on a [`ScopeManager`]. Expands to nothing unless the `trace` feature is
+/// enabled, so the event expression is never evaluated in normal builds.
+macro_rules! trace {
+    ($manager:expr, $event:expr) => {
+        #[cfg(feature = "trace")]
+        $manager.trace.push($event);
+    };
+}
+
+/// Where a variable is declared, from the start to the end of its name.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DefPosition {
+    pub start: Position,
+    pub end: Position,
+}
+
+impl DefPosition {
+    pub fn of(node: &impl Node) -> Option<Self> {
+        Some(Self {
+            start: node.start_position()?,
+            end: node.end_position()?,
+        })
+    }
+
+    /// The byte range of the name.
+    pub fn bytes(&self) -> std::ops::Range<usize> {
+        self.start.bytes()..self.end.bytes()
+    }
+
+    /// The range of the name, in LSP coordinates.
+    pub fn range(&self) -> Range {
+        Range {
+            start: lsp_position(self.start),
+            end: lsp_position(self.end),
+        }
+    }
 }
 
 #[derive(Debug)]
@@ -26,6 +99,11 @@ pub struct Scope {
     pub value_arena: SlotMap<ValueId, Value>,
     pub var_arena: SlotMap<VarId, Var>,
     pub var_names: LinkedHashMap<String, VarId>,
+    /// Annotations of the function values declared in this scope.
+    pub annotations: HashMap<ValueId, Annotations>,
+    /// Where the variables of this scope are declared. Variables bound without a name in the
+    /// source, like the exports of a required module, have no position.
+    pub def_positions: HashMap<VarId, DefPosition>,
     pub parent: Option<ScopeId>,
     pub name: Option<String>,
 }
@@ -36,6 +114,8 @@ impl Scope {
             value_arena: SlotMap::with_key(),
             var_arena: SlotMap::with_key(),
             var_names: LinkedHashMap::new(),
+            annotations: HashMap::new(),
+            def_positions: HashMap::new(),
             parent,
             name: None,
         }
@@ -46,6 +126,8 @@ impl Scope {
             value_arena: SlotMap::with_key(),
             var_arena: SlotMap::with_key(),
             var_names: LinkedHashMap::new(),
+            annotations: HashMap::new(),
+            def_positions: HashMap::new(),
             parent,
             name: Some(name),
         }
@@ -66,7 +148,14 @@ impl Scope {
         self.alloc_var(name, Var::Local(id))
     }
 
-    #[allow(unused)]
+    pub fn alloc_parameter(&mut self, name: String) -> VarId {
+        self.alloc_var(name, Var::Parameter)
+    }
+
+    pub fn alloc_unknown(&mut self, name: String) -> VarId {
+        self.alloc_var(name, Var::Unknown)
+    }
+
     pub fn alloc_reference(&mut self, name: String, scope: ScopeId, var: VarId) -> VarId {
         self.alloc_var(name, Var::Reference(scope, var))
     }
@@ -99,31 +188,315 @@ impl Scope {
     }
 }
 
+/// A place where the name of a local variable or parameter appears.
+#[derive(Debug, Clone)]
+pub struct Usage {
+    /// The scope declaring the variable.
+    pub scope: ScopeId,
+    /// The variable as declared, which identifies it even after it's reassigned.
+    pub var: VarId,
+    /// The byte range of the name.
+    pub bytes: std::ops::Range<usize>,
+    /// The range of the name, in LSP coordinates.
+    pub range: Range,
+    /// Whether the variable is declared or assigned here, rather than read.
+    pub write: bool,
+}
+
+/// A parenthesized call to a function whose parameters are known.
+#[derive(Debug, Clone)]
+pub struct CallSite {
+    /// The signature of the called function, e.g. `function name(a, b)`.
+    pub signature: String,
+    /// The parameters the arguments are matched with.
+    pub params: Vec<String>,
+    /// The byte range between the parentheses.
+    pub arguments: std::ops::Range<usize>,
+    /// Byte offsets of the commas separating the arguments.
+    pub commas: Vec<usize>,
+}
+
+impl CallSite {
+    /// The index of the parameter an argument at byte `offset` is passed to.
+    pub fn active_parameter(&self, offset: usize) -> usize {
+        self.commas.iter().filter(|&&comma| comma < offset).count()
+    }
+}
+
+#[derive(Debug)]
 pub struct ScopeManager {
-    pub(crate) ast: Ast,
+    pub(crate) ast: Arc<Ast>,
     pub(crate) scopes: SlotMap<ScopeId, Scope>,
     pub(crate) stack: Vec<ScopeId>,
-    pub(crate) node_refs: HashMap<usize, ScopeId>,
+    /// The scope opened for each block, by the byte range of the block's tokens. Nested blocks are
+    /// always separated by keywords, so no two blocks share a range. Empty blocks have no tokens,
+    /// and so no entry.
+    pub(crate) node_refs: HashMap<(usize, usize), ScopeId>,
+    /// The scope of the main chunk, the first block visited.
+    pub(crate) chunk: Option<ScopeId>,
+    /// Memoized [`ScopeManager::find_var_from`] results by name, then starting scope. Declaring a
+    /// name through the manager drops that name's entries, as only they can be shadowed by it.
+    lookup_cache: Mutex<HashMap<String, HashMap<ScopeId, Option<(ScopeId, VarId)>>>>,
     pub(crate) hints: Vec<InlayHint>,
+    pub(crate) diagnostics: Vec<Diagnostic>,
+    /// Signatures of the functions called by name, by the byte range of the callee name.
+    pub(crate) signatures: Vec<(std::ops::Range<usize>, String)>,
+    /// Calls to functions called by name, for signature help.
+    pub(crate) calls: Vec<CallSite>,
+    /// Where the functions called by name are defined, by the byte range of the callee name.
+    pub(crate) callee_definitions: Vec<(std::ops::Range<usize>, Range)>,
+    /// The required module and export key of the variables bound by `require` calls.
+    pub(crate) required: HashMap<(ScopeId, VarId), (String, String)>,
+    /// Calls to functions of required modules, by the byte range of the callee name, with the
+    /// module and the function's export key.
+    pub(crate) module_callees: Vec<(std::ops::Range<usize>, String, String)>,
+    /// Every declaration, read and assignment of a local variable or parameter.
+    pub(crate) usages: Vec<Usage>,
+    /// The lines spanned by function bodies and `do`, `while`, `repeat` and `for` statements.
+    pub(crate) folds: Vec<FoldingRange>,
+    /// The locals and parameters declared in each scope, by name. A name declared again in the
+    /// same scope is a new variable.
+    pub(crate) locals: HashMap<(ScopeId, String), VarId>,
+    /// Start offsets of the names targeted by assignments, whose usages are writes.
+    pub(crate) assigned: HashSet<usize>,
     pub(crate) name_stack: Vec<String>,
+    /// Parameters of the function body or loop variables of the `for` being visited, declared in
+    /// the next opened scope.
+    pub(crate) pending_params: Option<Vec<(String, DefPosition)>>,
+    /// The depths of the scope and name stacks when each statement being visited began, which they
+    /// must return to by its end.
+    pub(crate) stmt_depths: Vec<(usize, usize)>,
+    pub(crate) config: Arc<Config>,
+    pub(crate) modules: Arc<Modules>,
+    /// When set, only calls within this range emit hints. The whole scope tree is still built.
+    pub(crate) range: Option<Range>,
+    /// With `hintExportedOnly`, the byte offsets of the `end` tokens of the exported functions,
+    /// which are the only ones whose calls are hinted.
+    pub(crate) exported: Option<HashSet<usize>>,
+    /// The modules required while building, with the revision each was at, so that the scope tree
+    /// can be told apart from a current one once any of them changes.
+    dependencies: Mutex<HashMap<String, u64>>,
+    #[cfg(feature = "trace")]
+    pub(crate) trace: Vec<TraceEvent>,
 }
 
 impl ScopeManager {
-    pub fn new(ast: Ast) -> Self {
+    /// Creates a manager for `ast`. Nothing is known about it until [`ScopeManager::build`] is
+    /// called, after which the manager can answer any number of queries.
+    pub fn new(ast: impl Into<Arc<Ast>>, config: Arc<Config>, modules: Arc<Modules>) -> Self {
+        Self::new_in_range(ast, config, modules, None)
+    }
+
+    /// Like [`ScopeManager::new`], only emitting hints for arguments that start within `range`
+    /// (in LSP coordinates) when one is given. An empty range stands for the whole document.
+    pub fn new_in_range(
+        ast: impl Into<Arc<Ast>>,
+        config: Arc<Config>,
+        modules: Arc<Modules>,
+        range: Option<Range>,
+    ) -> Self {
         let mut scopes = SlotMap::with_key();
-        let global = scopes.insert(Scope::new_named(None, "global".to_string()));
-        let mut new = Self {
-            ast,
+        // Bundled definitions live in a scope above the globals, so user code shadows them.
+        let bundled = config
+            .stdlib
+            .then(definitions::stdlib)
+            .into_iter()
+            .chain(config.neovim_api.then(definitions::neovim))
+            .collect::<Vec<_>>();
+        let definitions = (!bundled.is_empty()).then(|| {
+            let mut scope = Scope::new_named(None, "definitions".to_string());
+            for (name, value) in bundled.into_iter().flatten() {
+                scope.alloc_local(name.clone(), value.clone());
+            }
+            scopes.insert(scope)
+        });
+        let global = scopes.insert(Scope::new_named(definitions, "global".to_string()));
+        Self {
+            ast: ast.into(),
             scopes,
             stack: vec![global],
             node_refs: HashMap::new(),
+            chunk: None,
+            lookup_cache: Mutex::default(),
             hints: vec![],
+            diagnostics: vec![],
+            signatures: vec![],
+            calls: vec![],
+            callee_definitions: vec![],
+            required: HashMap::new(),
+            module_callees: vec![],
+            usages: vec![],
+            folds: vec![],
+            locals: HashMap::new(),
+            assigned: HashSet::new(),
             name_stack: vec![],
+            pending_params: None,
+            stmt_depths: vec![],
+            config,
+            modules,
+            range,
+            exported: None,
+            dependencies: Mutex::default(),
+            #[cfg(feature = "trace")]
+            trace: vec![],
+        }
+    }
+
+    /// The AST the manager was created for.
+    pub fn ast(&self) -> &Ast {
+        &self.ast
+    }
+
+    /// Visits the AST, building its scope tree along with the hints and diagnostics of its calls.
+    pub fn build(mut self) -> Self {
+        if self.config.hint_exported_only {
+            self.exported = Some(self.exported_functions());
+        }
+        self.hoist_global_functions();
+        // The manager keeps its own handle on the AST, so the visit can borrow it while the scope
+        // tree is built.
+        let ast = self.ast.clone();
+        self.visit_ast(&ast);
+        self
+    }
+
+    /// Finds the functions `ast` exports, using a separate pass whose hints are discarded, as
+    /// exports are only known once the final `return` has been visited. The modules required by
+    /// that pass are dependencies of this one too.
+    fn exported_functions(&self) -> HashSet<usize> {
+        let config = Config {
+            hint_exported_only: false,
+            ..self.config.as_ref().clone()
+        };
+        let manager = Self::new(self.ast.clone(), Arc::new(config), self.modules.clone()).build();
+        self.dependencies
+            .lock()
+            .unwrap()
+            .extend(manager.dependencies.lock().unwrap().drain());
+        collect_exports(&manager)
+            .values()
+            .filter_map(|value| match value {
+                Value::Function((_, body)) => Some(body.end_token().start_position()?.bytes()),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// The exports of the module `name`, recording it as a dependency at its current revision.
+    pub(crate) fn module_exports(&self, name: &str) -> Option<Arc<Exports>> {
+        self.dependencies
+            .lock()
+            .unwrap()
+            .entry(name.to_string())
+            .or_insert_with(|| self.modules.revision(name));
+        self.modules.exports(name)
+    }
+
+    /// Whether none of the modules required while building changed since.
+    pub fn dependencies_current(&self) -> bool {
+        self.dependencies
+            .lock()
+            .unwrap()
+            .iter()
+            .all(|(name, revision)| self.modules.revision(name) == *revision)
+    }
+
+    /// Computes the hints for calls within `range` only, skipping construction of the rest.
+    #[allow(unused)]
+    pub fn hints_in_range(
+        ast: impl Into<Arc<Ast>>,
+        config: Arc<Config>,
+        modules: Arc<Modules>,
+        range: Range,
+    ) -> Vec<InlayHint> {
+        Self::new_in_range(ast, config, modules, Some(range))
+            .build()
+            .hints
+    }
+
+    /// The hints of the arguments that start within `range`, for a manager built for the whole
+    /// document so that it can be reused as the client's view moves. An empty range, as sent by
+    /// clients asking about the cursor's position, stands for the whole document.
+    pub fn hints_within(&self, range: Range) -> Vec<InlayHint> {
+        self.hints
+            .iter()
+            .filter(|hint| {
+                // Hints keep full_moon's 1-based positions.
+                let pos = lsp_types::Position {
+                    line: hint.position.line.saturating_sub(1),
+                    character: hint.position.character.saturating_sub(1),
+                };
+                range_contains(&range, pos)
+            })
+            .cloned()
+            .collect()
+    }
+
+    /// The signature of the function called by the name at byte `offset`, if any.
+    pub fn signature_at(&self, offset: usize) -> Option<&str> {
+        self.signatures
+            .iter()
+            .find(|(range, _)| range.contains(&offset))
+            .map(|(_, signature)| signature.as_str())
+    }
+
+    /// The innermost call whose parentheses contain byte `offset`, if its function is known.
+    pub fn call_at(&self, offset: usize) -> Option<&CallSite> {
+        self.calls
+            .iter()
+            .filter(|call| call.arguments.start <= offset && offset <= call.arguments.end)
+            .min_by_key(|call| call.arguments.len())
+    }
+
+    /// Where the function called by the name at byte `offset` is defined, if it's known.
+    pub fn definition_at(&self, offset: usize) -> Option<Range> {
+        self.callee_definitions
+            .iter()
+            .find(|(range, _)| range.contains(&offset))
+            .map(|(_, definition)| *definition)
+    }
+
+    /// The required module and export key of the function called by the name at byte `offset`,
+    /// for functions defined in another file.
+    pub fn module_export_at(&self, offset: usize) -> Option<(&str, &str)> {
+        self.module_callees
+            .iter()
+            .find(|(range, _, _)| range.contains(&offset))
+            .map(|(_, module, export)| (module.as_str(), export.as_str()))
+    }
+
+    /// The foldable functions and blocks, in the order they start.
+    pub fn folding_ranges(&self) -> &[FoldingRange] {
+        &self.folds
+    }
+
+    /// All usages of the local or parameter whose name is at byte `offset`, including its
+    /// declaration, in document order.
+    pub fn usages_at(&self, offset: usize) -> Vec<&Usage> {
+        // The cursor can be right after the name, as when it was just typed.
+        let Some(target) = self
+            .usages
+            .iter()
+            .find(|usage| usage.bytes.start <= offset && offset <= usage.bytes.end)
+        else {
+            return vec![];
+        };
+        let mut usages = self
+            .usages
+            .iter()
+            .filter(|usage| usage.scope == target.scope && usage.var == target.var)
+            .collect::<Vec<_>>();
+        // Locals are declared after their initializers are visited.
+        usages.sort_by_key(|usage| usage.bytes.start);
+        usages
+    }
+
+    /// Whether a hint at `pos` falls within the requested range, if any. The range is half-open.
+    pub fn in_range(&self, pos: full_moon::tokenizer::Position) -> bool {
+        let Some(range) = self.range else {
+            return true;
         };
-        // Safety: We're not modifying the AST and the pointer will remain valid throughout the pass as the
-        // manager owns the AST.
-        new.visit_ast(unsafe { (&new.ast as *const Ast).as_ref().unwrap_unchecked() });
-        new
+        range_contains(&range, lsp_position(pos))
     }
 
     #[allow(unused)]
@@ -135,61 +508,112 @@ impl ScopeManager {
         self.scopes.get(scope)?.value_arena.get(value)
     }
 
+    /// Follows `var` through the variables it's an alias of, to the one holding its value.
+    pub fn resolve_var(&self, scope: ScopeId, var: VarId) -> Option<(ScopeId, VarId)> {
+        match self.scopes.get(scope)?.var_arena.get(var)? {
+            Var::Reference(scope, var) => self.resolve_var(*scope, *var),
+            _ => Some((scope, var)),
+        }
+    }
+
     pub fn resolve_reference(&self, scope: ScopeId, var: VarId) -> Option<(ScopeId, ValueId)> {
         let v = self.scopes.get(scope)?.var_arena.get(var)?;
         match v {
             Var::Reference(scope, var) => self.resolve_reference(*scope, *var),
             Var::Local(value) => Some((scope, *value)),
+            Var::Parameter | Var::Unknown => None,
         }
     }
 
+    /// The value held by the variable `var` declared in `scope`, following references.
+    pub fn var_value(&self, scope: ScopeId, var: VarId) -> Option<&Value> {
+        let (scope, value) = self.resolve_reference(scope, var)?;
+        self.get_value(scope, value)
+    }
+
     pub fn find_var(&self, name: &str) -> Option<Var> {
-        let Some(mut id) = self.stack.last().copied() else {
-        	return None;
-        };
-        let mut original = true;
+        let current = self.stack.last().copied()?;
+        let (scope, var) = self.find_var_scope(name)?;
+        if scope == current {
+            self.scopes.get(scope)?.var_arena.get(var).copied()
+        } else {
+            Some(Var::Reference(scope, var))
+        }
+    }
+
+    /// Finds the innermost scope, starting from the current one, that declares `name`.
+    pub fn find_var_scope(&self, name: &str) -> Option<(ScopeId, VarId)> {
+        self.find_var_from(self.stack.last().copied()?, name)
+    }
+
+    /// Finds the innermost scope, starting from `id`, that declares `name`.
+    pub fn find_var_from(&self, id: ScopeId, name: &str) -> Option<(ScopeId, VarId)> {
+        if let Some(found) = self
+            .lookup_cache
+            .lock()
+            .unwrap()
+            .get(name)
+            .and_then(|starts| starts.get(&id))
+        {
+            return *found;
+        }
+        let found = self.walk_scopes(id, name);
+        self.lookup_cache
+            .lock()
+            .unwrap()
+            .entry(name.to_string())
+            .or_default()
+            .insert(id, found);
+        found
+    }
+
+    fn walk_scopes(&self, mut id: ScopeId, name: &str) -> Option<(ScopeId, VarId)> {
         loop {
-            let Some(scope) = self.scopes.get(id) else {
-				return None;
-			};
+            let scope = self.scopes.get(id)?;
             if let Some(var) = scope.var_names.get(name) {
-                if original {
-                    let var = scope.var_arena.get(*var)?;
-                    return Some(*var);
-                } else {
-                    return Some(Var::Reference(id, *var));
-                }
+                return Some((id, *var));
             }
-            if let Some(parent) = scope.parent {
-                original = false;
-                id = parent;
-            } else {
-                break;
-            };
+            id = scope.parent?;
         }
-        None
     }
 
     #[allow(unused)]
-    pub fn open_scope_named(&mut self, name: impl Into<String>, node: *const dyn Node) -> ScopeId {
+    pub fn open_scope_named(&mut self, name: impl Into<String>, node: &dyn Node) -> ScopeId {
         let scope = self
             .scopes
             .insert(Scope::new_named(self.stack.last().copied(), name.into()));
-        self.node_refs.insert(node as *const () as usize, scope);
+        if let Some(key) = node_key(node) {
+            self.node_refs.insert(key, scope);
+        }
         self.stack.push(scope);
+        trace!(
+            self,
+            TraceEvent::OpenScope {
+                scope,
+                name: self.scopes[scope].name.clone(),
+            }
+        );
         scope
     }
 
-    pub fn open_scope(&mut self, node: *const dyn Node) -> ScopeId {
+    pub fn open_scope(&mut self, node: &dyn Node) -> ScopeId {
         let scope = if let Some(name) = self.name_stack.pop() {
             self.scopes
                 .insert(Scope::new_named(self.stack.last().copied(), name))
         } else {
             self.scopes.insert(Scope::new(self.stack.last().copied()))
         };
-        self.node_refs
-            .insert(node as *const dyn Node as *const () as usize, scope);
+        if let Some(key) = node_key(node) {
+            self.node_refs.insert(key, scope);
+        }
         self.stack.push(scope);
+        trace!(
+            self,
+            TraceEvent::OpenScope {
+                scope,
+                name: self.scopes[scope].name.clone(),
+            }
+        );
         scope
     }
 
@@ -198,12 +622,116 @@ impl ScopeManager {
     }
 
     pub fn close_scope(&mut self) {
-        self.stack.pop();
+        let _scope = self.stack.pop();
+        trace!(self, TraceEvent::CloseScope { scope: _scope });
+    }
+
+    /// Declares a parameter named `name` in the given scope.
+    pub fn declare_parameter(&mut self, scope: ScopeId, name: String) -> Option<VarId> {
+        self.lookup_cache.get_mut().unwrap().remove(&name);
+        Some(self.scopes.get_mut(scope)?.alloc_parameter(name))
+    }
+
+    /// Declares a local named `name` whose value is unknown in the given scope.
+    pub fn declare_unknown(&mut self, scope: ScopeId, name: String) -> Option<VarId> {
+        self.lookup_cache.get_mut().unwrap().remove(&name);
+        Some(self.scopes.get_mut(scope)?.alloc_unknown(name))
     }
 
+    /// Declares `name` in the given scope as an alias of the variable `var` in `target`.
+    pub fn define_reference(
+        &mut self,
+        scope: ScopeId,
+        name: String,
+        target: ScopeId,
+        var: VarId,
+    ) -> Option<VarId> {
+        self.lookup_cache.get_mut().unwrap().remove(&name);
+        Some(
+            self.scopes
+                .get_mut(scope)?
+                .alloc_reference(name, target, var),
+        )
+    }
+
+    /// Allocates a local named `name` holding `value` in the given scope.
+    pub fn define(&mut self, scope: ScopeId, name: String, value: Value) -> Option<VarId> {
+        self.lookup_cache.get_mut().unwrap().remove(&name);
+        trace!(
+            self,
+            TraceEvent::AllocLocal {
+                scope,
+                name: name.clone(),
+            }
+        );
+        Some(self.scopes.get_mut(scope)?.alloc_local(name, value))
+    }
+
+    /// Attaches annotations to the value of the local `name` declared in `scope`.
+    pub fn annotate(&mut self, scope: ScopeId, name: &str, annotations: Annotations) {
+        if annotations.is_empty() {
+            return;
+        }
+        let Some(scope) = self.scopes.get_mut(scope) else {
+            return;
+        };
+        if let Some(Var::Local(value)) = scope.get_var(name).copied() {
+            scope.annotations.insert(value, annotations);
+        }
+    }
+
+    /// Records the name `node` as where the variable `name` of `scope` was last declared.
+    pub fn place(&mut self, scope: ScopeId, name: &str, node: &impl Node) {
+        let Some(scope) = self.scopes.get_mut(scope) else {
+            return;
+        };
+        if let (Some(var), Some(position)) = (scope.get_var_id(name), DefPosition::of(node)) {
+            scope.def_positions.insert(var, position);
+        }
+    }
+
+    /// Where the variable `var` of `scope` is declared, if it was declared by name.
+    pub fn def_position(&self, scope: ScopeId, var: VarId) -> Option<DefPosition> {
+        self.scopes.get(scope)?.def_positions.get(&var).copied()
+    }
+
+    pub fn get_annotations(&self, scope: ScopeId, value: ValueId) -> Option<&Annotations> {
+        self.scopes.get(scope)?.annotations.get(&value)
+    }
+
+    pub fn push_hint(&mut self, hint: InlayHint) {
+        trace!(
+            self,
+            TraceEvent::Hint {
+                label: match &hint.label {
+                    lsp_types::InlayHintLabel::String(label) => label.clone(),
+                    lsp_types::InlayHintLabel::LabelParts(parts) => {
+                        parts.iter().map(|part| part.value.as_str()).collect()
+                    }
+                },
+                line: hint.position.line,
+                character: hint.position.character,
+            }
+        );
+        self.hints.push(hint);
+    }
+
+    /// Takes the scope operations recorded so far, leaving the trace empty.
+    #[cfg(feature = "trace")]
+    pub fn drain_trace(&mut self) -> Vec<TraceEvent> {
+        std::mem::take(&mut self.trace)
+    }
+
+    /// The scope opened for the block `node`, which can come from any copy of the AST the manager
+    /// was built for.
     #[allow(unused)]
-    pub fn get_scope_id(&self, node: *const dyn Node) -> Option<ScopeId> {
-        self.node_refs.get(&(node as *const () as usize)).copied()
+    pub fn get_scope_id(&self, node: &dyn Node) -> Option<ScopeId> {
+        self.get_scope_id_by_range(node_key(node)?)
+    }
+
+    /// The scope opened for the block whose tokens span the byte range `start..end`.
+    pub fn get_scope_id_by_range(&self, range: (usize, usize)) -> Option<ScopeId> {
+        self.node_refs.get(&range).copied()
     }
 
     #[allow(unused)]
@@ -230,3 +758,124 @@ impl ScopeManager {
         self.stack.last().copied()
     }
 }
+
+impl ScopeManager {
+    /// Checks the invariants of the scope tree built by the pass, returning a description of each
+    /// violation found.
+    pub fn validate(&self) -> Vec<String> {
+        let mut problems = vec![];
+        if self.stack.len() != 1 {
+            problems.push(format!(
+                "expected only the global scope on the stack after the pass, found {}",
+                self.stack.len()
+            ));
+        }
+        for (id, scope) in self.scopes.iter() {
+            if let Some(parent) = scope.parent {
+                if !self.scopes.contains_key(parent) {
+                    problems.push(format!("scope {id:?} has a missing parent {parent:?}"));
+                }
+            }
+            for (name, var) in scope.var_names.iter() {
+                // Follow references until they end, remembering each step to detect cycles.
+                let mut seen = HashSet::new();
+                let (mut scope_id, mut var) = (id, *var);
+                loop {
+                    if !seen.insert((scope_id, var)) {
+                        problems.push(format!("`{name}` in scope {id:?} is a reference cycle"));
+                        break;
+                    }
+                    match self.scopes.get(scope_id).and_then(|s| s.var_arena.get(var)) {
+                        Some(Var::Reference(next_scope, next_var)) => {
+                            (scope_id, var) = (*next_scope, *next_var);
+                        }
+                        Some(Var::Local(value)) => {
+                            if self.get_value(scope_id, *value).is_none() {
+                                problems.push(format!(
+                                    "`{name}` in scope {id:?} refers to a missing value"
+                                ));
+                            }
+                            break;
+                        }
+                        Some(Var::Parameter | Var::Unknown) => break,
+                        None => {
+                            problems.push(format!(
+                                "`{name}` in scope {id:?} refers to a missing variable"
+                            ));
+                            break;
+                        }
+                    }
+                }
+            }
+        }
+        for scope in self.node_refs.values() {
+            if !self.scopes.contains_key(*scope) {
+                problems.push(format!("a node refers to the missing scope {scope:?}"));
+            }
+        }
+        problems
+    }
+
+    fn fmt_scope(
+        &self,
+        f: &mut std::fmt::Formatter<'_>,
+        children: &HashMap<ScopeId, Vec<ScopeId>>,
+        id: ScopeId,
+        depth: usize,
+    ) -> std::fmt::Result {
+        let scope = &self.scopes[id];
+        let indent = "  ".repeat(depth);
+        writeln!(f, "{indent}{}", scope.name().unwrap_or("<block>"))?;
+        for (name, var) in scope.var_names.iter() {
+            let kind = match scope.var_arena.get(*var) {
+                Some(Var::Parameter) => "parameter".to_string(),
+                Some(Var::Unknown) => "local".to_string(),
+                Some(Var::Reference(..)) => "reference".to_string(),
+                Some(Var::Local(value)) => match scope.value_arena.get(*value) {
+                    Some(Value::Function((_, body))) => {
+                        let params = parameters(body);
+                        let params = params.iter().map(slot_name).collect::<Vec<_>>();
+                        format!("function/{}({})", params.len(), params.join(", "))
+                    }
+                    Some(Value::TableConstructor(_)) => "table".to_string(),
+                    _ => "value".to_string(),
+                },
+                None => "unknown".to_string(),
+            };
+            writeln!(f, "{indent}  {name}: {kind}")?;
+        }
+        for child in children.get(&id).into_iter().flatten() {
+            self.fmt_scope(f, children, *child, depth + 1)?;
+        }
+        Ok(())
+    }
+}
+
+/// Whether `pos` is within the half-open `range`. An empty range contains every position.
+fn range_contains(range: &Range, pos: lsp_types::Position) -> bool {
+    range.start == range.end || (range.start <= pos && pos < range.end)
+}
+
+/// Identifies a node by the byte range of its tokens, which is the same in every copy of an AST.
+pub(crate) fn node_key(node: &dyn Node) -> Option<(usize, usize)> {
+    let (start, end) = node.range()?;
+    Some((start.bytes(), end.bytes()))
+}
+
+/// Renders the scope hierarchy as an indented tree of scopes and the variables they declare.
+impl std::fmt::Display for ScopeManager {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let mut children = HashMap::<ScopeId, Vec<ScopeId>>::new();
+        let mut roots = vec![];
+        for (id, scope) in self.scopes.iter() {
+            match scope.parent {
+                Some(parent) => children.entry(parent).or_default().push(id),
+                None => roots.push(id),
+            }
+        }
+        for root in roots {
+            self.fmt_scope(f, &children, root, 0)?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,170 @@
+use std::collections::HashMap;
+
+use full_moon::ast::{
+    Assignment, Ast, Expression, Field, FunctionBody, FunctionDeclaration, LocalAssignment,
+    LocalFunction, TableConstructor, Value,
+};
+use full_moon::node::Node;
+use full_moon::visitors::Visitor;
+use lsp_types::{DocumentSymbol, Range, SymbolKind};
+
+use crate::scope::node_key;
+use crate::visitor::{lsp_range, name_of, node_name, parameters, slot_name};
+
+/// The named functions of a document, nested within the functions that declare them.
+pub fn document_symbols(ast: &Ast) -> Vec<DocumentSymbol> {
+    let mut collector = SymbolCollector::default();
+    collector.visit_ast(ast);
+    collector.symbols
+}
+
+/// The name of a function and the ranges of its declaration and of the name itself.
+struct Declaration {
+    name: String,
+    range: Range,
+    selection_range: Range,
+}
+
+/// Collects the named functions of a document. Declarations are recorded by the byte range of the
+/// function's body, and become symbols when that body is visited, so that the functions declared
+/// within it are nested under it.
+#[derive(Default)]
+struct SymbolCollector {
+    declarations: HashMap<(usize, usize), Declaration>,
+    /// The symbols of the named functions being visited, innermost last.
+    open: Vec<DocumentSymbol>,
+    /// Whether each function body being visited opened a symbol. Anonymous functions don't, so
+    /// the functions they declare are nested under the closest named one.
+    named: Vec<bool>,
+    symbols: Vec<DocumentSymbol>,
+}
+
+impl SymbolCollector {
+    /// Records the function `body` as assigned to `name_node`, in a declaration spanning from the
+    /// start of the name to the end of the function.
+    fn declare(&mut self, name: String, name_node: &impl Node, body: &FunctionBody) {
+        let (Some(selection_range), Some(end)) = (lsp_range(name_node), lsp_range(body)) else {
+            return;
+        };
+        self.declare_with_range(
+            name,
+            Range {
+                start: selection_range.start,
+                end: end.end,
+            },
+            selection_range,
+            body,
+        );
+    }
+
+    fn declare_with_range(
+        &mut self,
+        name: String,
+        range: Range,
+        selection_range: Range,
+        body: &FunctionBody,
+    ) {
+        let Some(key) = node_key(body) else {
+            return;
+        };
+        self.declarations.insert(
+            key,
+            Declaration {
+                name,
+                range,
+                selection_range,
+            },
+        );
+    }
+}
+
+/// The function an expression evaluates to, if it's a function literal.
+fn function_value(expr: &Expression) -> Option<&FunctionBody> {
+    match expr {
+        Expression::Value { value, .. } => match value.as_ref() {
+            Value::Function((_, body)) => Some(body),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+impl Visitor for SymbolCollector {
+    fn visit_local_function(&mut self, node: &LocalFunction) {
+        let (Some(range), Some(selection_range)) = (lsp_range(node), lsp_range(node.name())) else {
+            return;
+        };
+        let name = name_of(node.name());
+        self.declare_with_range(name, range, selection_range, node.body());
+    }
+
+    fn visit_function_declaration(&mut self, node: &FunctionDeclaration) {
+        let (Some(range), Some(selection_range)) = (lsp_range(node), lsp_range(node.name())) else {
+            return;
+        };
+        let name = node_name(node.name());
+        self.declare_with_range(name, range, selection_range, node.body());
+    }
+
+    fn visit_local_assignment(&mut self, node: &LocalAssignment) {
+        for (name, expr) in node.names().iter().zip(node.expressions().iter()) {
+            if let Some(body) = function_value(expr) {
+                self.declare(name_of(name), name, body);
+            }
+        }
+    }
+
+    fn visit_assignment(&mut self, node: &Assignment) {
+        for (var, expr) in node.variables().iter().zip(node.expressions().iter()) {
+            if let Some(body) = function_value(expr) {
+                self.declare(node_name(var), var, body);
+            }
+        }
+    }
+
+    fn visit_table_constructor(&mut self, node: &TableConstructor) {
+        for field in node.fields() {
+            if let Field::NameKey { key, value, .. } = field {
+                if let Some(body) = function_value(value) {
+                    self.declare(name_of(key), key, body);
+                }
+            }
+        }
+    }
+
+    fn visit_function_body(&mut self, node: &FunctionBody) {
+        let Some(declaration) = node_key(node).and_then(|key| self.declarations.remove(&key))
+        else {
+            self.named.push(false);
+            return;
+        };
+        let params = parameters(node);
+        let params = params.iter().map(slot_name).collect::<Vec<_>>();
+        #[allow(deprecated)]
+        let symbol = DocumentSymbol {
+            name: declaration.name,
+            detail: Some(format!("({})", params.join(", "))),
+            kind: SymbolKind::FUNCTION,
+            tags: None,
+            deprecated: None,
+            range: declaration.range,
+            selection_range: declaration.selection_range,
+            children: None,
+        };
+        self.open.push(symbol);
+        self.named.push(true);
+    }
+
+    fn visit_function_body_end(&mut self, _node: &FunctionBody) {
+        if !self.named.pop().unwrap_or(false) {
+            return;
+        }
+        let Some(symbol) = self.open.pop() else {
+            return;
+        };
+        match self.open.last_mut() {
+            Some(parent) => parent.children.get_or_insert_with(Vec::new).push(symbol),
+            None => self.symbols.push(symbol),
+        }
+    }
+}
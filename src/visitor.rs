@@ -1,75 +1,1238 @@
+use std::collections::HashMap;
+
 use full_moon::ast::{
-    Call, Expression, FunctionArgs, FunctionCall, FunctionDeclaration, Suffix, TableConstructor,
-    Value,
+    BinOp, Block, Call, Expression, FunctionArgs, FunctionBody, FunctionCall, FunctionDeclaration,
+    Index, LastStmt, Parameter, Prefix, Stmt, Suffix, TableConstructor, Value, VarExpression,
 };
 use full_moon::node::Node;
+use full_moon::tokenizer::{Symbol, TokenReference, TokenType};
 use full_moon::visitors::Visitor;
 use tower_lsp::lsp_types::*;
 
-use crate::scope::{ScopeManager, Var};
+use crate::annotations::Annotations;
+use crate::scope::{CallSite, DefPosition, ScopeId, ScopeManager, Usage, ValueId, Var, VarId};
+
+/// Words reserved by Lua, which can't name variables.
+const KEYWORDS: &[&str] = &[
+    "and", "break", "do", "else", "elseif", "end", "false", "for", "function", "goto", "if", "in",
+    "local", "nil", "not", "or", "repeat", "return", "then", "true", "until", "while",
+];
+
+/// Whether `name` is a plain Lua identifier.
+pub(crate) fn is_identifier(name: &str) -> bool {
+    if KEYWORDS.contains(&name) {
+        return false;
+    }
+    let mut chars = name.chars();
+    chars
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+/// The text of a name token, without the whitespace and comments around it.
+pub fn name_of(token: &TokenReference) -> String {
+    token.token().to_string()
+}
+
+/// The text of a node's tokens without the whitespace and comments around and between them, as in
+/// `M.f` for `M . f` or `M --[[ x ]].f`.
+pub fn node_name(node: &impl Node) -> String {
+    node.tokens()
+        .map(|token| token.token().to_string())
+        .collect()
+}
+
+/// The name a parameter declares, or `...`. Type annotations and attributes aren't part of the
+/// parameter's token, so they never end up in the name.
+pub fn parameter_name(param: &Parameter) -> Option<String> {
+    match param {
+        Parameter::Name(token) => Some(name_of(token)),
+        Parameter::Ellipse(_) => Some("...".to_string()),
+        _ => None,
+    }
+}
+
+/// The names and positions of a function's parameters, one slot per parameter.
+pub type Parameters = Vec<Option<(String, full_moon::tokenizer::Position)>>;
+
+/// Extracts the names and positions of a function's parameters. Entries that are neither an
+/// identifier nor `...`, such as those from a partially recovered signature, leave their slot
+/// empty so they never end up as hint labels, while the parameters after them keep their
+/// positions.
+pub fn parameters(body: &FunctionBody) -> Parameters {
+    body.parameters()
+        .iter()
+        .map(|p| {
+            let name = parameter_name(p)?;
+            (name == "..." || is_identifier(&name))
+                .then(|| (name, p.start_position().unwrap_or_default()))
+        })
+        .collect()
+}
+
+/// `count` arguments, as in `1 argument` or `2 arguments`.
+fn count_arguments(count: usize) -> String {
+    match count {
+        1 => "1 argument".to_string(),
+        _ => format!("{count} arguments"),
+    }
+}
+
+/// The last part of a callee's name, as in `move` for `move`, `M.move` or `obj:move`.
+fn short_name(callee: &str) -> &str {
+    callee
+        .rsplit(|c| c == '.' || c == ':')
+        .next()
+        .unwrap_or(callee)
+}
+
+/// The name of a parameter slot, or `?` for an entry that isn't a valid parameter.
+pub fn slot_name(slot: &Option<(String, full_moon::tokenizer::Position)>) -> &str {
+    slot.as_ref().map_or("?", |(name, _)| name.as_str())
+}
+
+/// Splits `require("mod")` or `require("mod").a.b` into the module name and the field path.
+fn require_target(value: &Value) -> Option<(String, Vec<String>)> {
+    let (prefix, mut suffixes): (_, Box<dyn Iterator<Item = &Suffix>>) = match value {
+        Value::FunctionCall(call) => (call.prefix(), Box::new(call.suffixes())),
+        Value::Var(full_moon::ast::Var::Expression(var)) => {
+            (var.prefix(), Box::new(var.suffixes()))
+        }
+        _ => return None,
+    };
+    match prefix {
+        Prefix::Name(name) if name.token().to_string() == "require" => {}
+        _ => return None,
+    }
+    let module = match suffixes.next()? {
+        Suffix::Call(Call::AnonymousCall(args)) => string_argument(args)?,
+        _ => return None,
+    };
+    let path = suffixes
+        .map(|suffix| match suffix {
+            Suffix::Index(Index::Dot { name, .. }) => Some(name.token().to_string()),
+            _ => None,
+        })
+        .collect::<Option<Vec<_>>>()?;
+    Some((module, path))
+}
+
+/// The contents of a call's only argument, if it's a string literal.
+fn string_argument(args: &FunctionArgs) -> Option<String> {
+    match args {
+        FunctionArgs::String(token) => match token.token_type() {
+            TokenType::StringLiteral { literal, .. } => Some(literal.to_string()),
+            _ => None,
+        },
+        FunctionArgs::Parentheses { arguments, .. } if arguments.len() == 1 => {
+            string_literal(arguments.iter().next()?)
+        }
+        _ => None,
+    }
+}
+
+/// The contents of `expr`, if it's a string literal.
+fn string_literal(expr: &Expression) -> Option<String> {
+    let Expression::Value { value, .. } = expr else {
+        return None;
+    };
+    let Value::String(token) = value.as_ref() else {
+        return None;
+    };
+    match token.token_type() {
+        TokenType::StringLiteral { literal, .. } => Some(literal.to_string()),
+        _ => None,
+    }
+}
+
+/// Splits a call like `a.b.c(x)` into the callee's dotted name and its arguments, matching how
+/// dotted declarations are stored. Indexing with a string literal, as in `a["b"](x)`, is the same
+/// as a dot. Calls with other suffixes before the arguments, like `a[i](x)` or `a:b(x)`, aren't
+/// split.
+fn dotted_call(call: &FunctionCall) -> Option<(String, &FunctionArgs)> {
+    let Prefix::Name(base) = call.prefix() else {
+        return None;
+    };
+    let mut name = base.token().to_string();
+    for suffix in call.suffixes() {
+        match suffix {
+            Suffix::Index(Index::Dot { name: field, .. }) => {
+                name.push('.');
+                name.push_str(&field.token().to_string());
+            }
+            Suffix::Index(Index::Brackets { expression, .. }) => {
+                name.push('.');
+                name.push_str(&string_literal(expression)?);
+            }
+            Suffix::Call(Call::AnonymousCall(args)) => return Some((name, args)),
+            _ => return None,
+        }
+    }
+    None
+}
+
+/// The dotted name of a variable like `a.b.c` or `a["b"]`, matching how dotted declarations are
+/// stored. Variables indexed by anything but names and string literals, or through calls, have
+/// none.
+fn dotted_var(var: &VarExpression) -> Option<String> {
+    let Prefix::Name(base) = var.prefix() else {
+        return None;
+    };
+    let mut name = base.token().to_string();
+    for suffix in var.suffixes() {
+        match suffix {
+            Suffix::Index(Index::Dot { name: field, .. }) => {
+                name.push('.');
+                name.push_str(&field.token().to_string());
+            }
+            Suffix::Index(Index::Brackets { expression, .. }) => {
+                name.push('.');
+                name.push_str(&string_literal(expression)?);
+            }
+            _ => return None,
+        }
+    }
+    Some(name)
+}
+
+/// The LSP range covered by `node`.
+pub(crate) fn lsp_range(node: &impl Node) -> Option<Range> {
+    Some(Range {
+        start: lsp_position(node.start_position()?),
+        end: lsp_position(node.end_position()?),
+    })
+}
+
+pub(crate) fn lsp_position(pos: full_moon::tokenizer::Position) -> Position {
+    // full_moon positions are 1-based, LSP positions are 0-based.
+    Position {
+        line: pos.line().saturating_sub(1) as u32,
+        character: pos.character().saturating_sub(1) as u32,
+    }
+}
+
+/// A variable bound to a required module or one of its exports.
+struct RequireBinding {
+    /// The variable's dotted name.
+    name: String,
+    /// The key of the export within the module, empty for the module itself.
+    export: String,
+    value: Value,
+}
+
+/// The receiver of a method call.
+enum Receiver {
+    /// A variable, possibly dotted, like `obj` or `M.obj`.
+    Name(String),
+    /// A required module and the field path within it, like `require("socket").tcp`.
+    Require(String, Vec<String>),
+}
+
+/// Splits a method call like `obj:m(x)`, `M.obj:m(x)` or `require("mod"):m(x)` into its receiver,
+/// the method name and the arguments.
+fn method_call(call: &FunctionCall) -> Option<(Receiver, String, &FunctionArgs)> {
+    let Prefix::Name(base) = call.prefix() else {
+        return None;
+    };
+    let base = base.token().to_string();
+    let mut suffixes = call.suffixes();
+    let mut receiver = if base == "require" {
+        match suffixes.next()? {
+            Suffix::Call(Call::AnonymousCall(args)) => {
+                Receiver::Require(string_argument(args)?, vec![])
+            }
+            _ => return None,
+        }
+    } else {
+        Receiver::Name(base)
+    };
+    for suffix in suffixes {
+        let field = match suffix {
+            Suffix::Index(Index::Dot { name, .. }) => name.token().to_string(),
+            Suffix::Index(Index::Brackets { expression, .. }) => string_literal(expression)?,
+            Suffix::Call(Call::MethodCall(method)) => {
+                return Some((receiver, method.name().token().to_string(), method.args()));
+            }
+            _ => return None,
+        };
+        match &mut receiver {
+            Receiver::Name(name) => {
+                name.push('.');
+                name.push_str(&field);
+            }
+            Receiver::Require(_, path) => path.push(field),
+        }
+    }
+    None
+}
+
+/// Splits `ok and require("a") or require("b")` into the primary and fallback requires.
+fn conditional_require(expr: &Expression) -> Option<(&Value, &Value)> {
+    let Expression::BinaryOperator {
+        lhs,
+        binop: BinOp::Or(_),
+        rhs: fallback,
+    } = expr
+    else {
+        return None;
+    };
+    let Expression::BinaryOperator {
+        binop: BinOp::And(_),
+        rhs: primary,
+        ..
+    } = lhs.as_ref()
+    else {
+        return None;
+    };
+    let (
+        Expression::Value { value: primary, .. },
+        Expression::Value {
+            value: fallback, ..
+        },
+    ) = (primary.as_ref(), fallback.as_ref())
+    else {
+        return None;
+    };
+    (require_target(primary).is_some() && require_target(fallback).is_some())
+        .then(|| (primary.as_ref(), fallback.as_ref()))
+}
+
+/// Whether two values would produce the same hints: functions with the same parameter names, or
+/// two values that aren't functions.
+fn same_shape(a: &Value, b: &Value) -> bool {
+    match (a, b) {
+        (Value::Function((_, a)), Value::Function((_, b))) => {
+            let names = |body: &FunctionBody| {
+                parameters(body)
+                    .iter()
+                    .map(|slot| slot_name(slot).to_string())
+                    .collect::<Vec<_>>()
+            };
+            names(a) == names(b)
+        }
+        (Value::Function(_), _) | (_, Value::Function(_)) => false,
+        _ => true,
+    }
+}
+
+/// The value an assignment stores. The idempotent module guard `M = M or {}` (or
+/// `rawget(_G, "M") or {}`) is seen through to its fallback table, so fields defined on `M`
+/// afterwards still have a table to belong to.
+fn assigned_value(expr: &Expression) -> Option<&Value> {
+    match expr {
+        Expression::Value { value, .. } => Some(value.as_ref()),
+        Expression::BinaryOperator {
+            binop: BinOp::Or(_),
+            rhs,
+            ..
+        } => match rhs.as_ref() {
+            Expression::Value { value, .. }
+                if matches!(value.as_ref(), Value::TableConstructor(_)) =>
+            {
+                Some(value.as_ref())
+            }
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+/// The kind of a literal value, as in `table` or `string`.
+fn value_kind(value: &Value) -> Option<&'static str> {
+    match value {
+        Value::TableConstructor(_) => Some("table"),
+        Value::Function(_) => Some("function"),
+        Value::String(_) => Some("string"),
+        Value::Number(_) => Some("number"),
+        Value::Symbol(token) => match token.token_type() {
+            TokenType::Symbol {
+                symbol: Symbol::True | Symbol::False,
+            } => Some("boolean"),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+/// What a local declared directly in a block was initialized with.
+enum Local<'a> {
+    Value(&'a Value),
+    Function(&'a FunctionBody),
+}
+
+/// What the local `name` declared directly in `block` was last initialized with.
+fn local_initializer<'a>(block: &'a Block, name: &str) -> Option<Local<'a>> {
+    block
+        .stmts()
+        .filter_map(|stmt| match stmt {
+            Stmt::LocalAssignment(local) => local
+                .names()
+                .iter()
+                .zip(local.expressions().iter())
+                .find(|(declared, _)| declared.token().to_string() == name)
+                .map(|(_, expr)| assigned_value(expr).map(Local::Value)),
+            Stmt::LocalFunction(local) if local.name().token().to_string() == name => {
+                Some(Some(Local::Function(local.body())))
+            }
+            _ => None,
+        })
+        .last()
+        .flatten()
+}
+
+/// The single expression `f` returns at its end, if any.
+fn single_return(f: &FunctionBody) -> Option<&Expression> {
+    let Some(LastStmt::Return(ret)) = f.block().last_stmt() else {
+        return None;
+    };
+    let mut returns = ret.returns().iter();
+    match (returns.next(), returns.next()) {
+        (Some(expr), None) => Some(expr),
+        _ => None,
+    }
+}
+
+/// The kind of the single value `f` returns at its end, if it's a literal or a local of the
+/// function initialized with one.
+fn return_kind(f: &FunctionBody) -> Option<&'static str> {
+    match assigned_value(single_return(f)?)? {
+        Value::Var(full_moon::ast::Var::Name(name)) => {
+            match local_initializer(f.block(), &name.token().to_string())? {
+                Local::Value(value) => value_kind(value),
+                Local::Function(_) => Some("function"),
+            }
+        }
+        value => value_kind(value),
+    }
+}
+
+/// The function `f` returns at its end, if it's a function literal or a local function of `f`.
+fn returned_function(f: &FunctionBody) -> Option<&FunctionBody> {
+    match assigned_value(single_return(f)?)? {
+        Value::Function((_, body)) => Some(body),
+        Value::Var(full_moon::ast::Var::Name(name)) => {
+            match local_initializer(f.block(), &name.token().to_string())? {
+                Local::Value(Value::Function((_, body))) => Some(body),
+                Local::Function(body) => Some(body),
+                Local::Value(_) => None,
+            }
+        }
+        _ => None,
+    }
+}
+
+/// The signature of a function as it would be declared, e.g. `function name(a, b)`.
+fn signature(name: &str, params: &[Option<(String, full_moon::tokenizer::Position)>]) -> String {
+    let params = params.iter().map(slot_name).collect::<Vec<_>>();
+    format!("function {name}({})", params.join(", "))
+}
+
+/// A call argument. The call sugar `f "s"` and `f { ... }` passes its argument without an
+/// expression around it.
+enum Argument<'a> {
+    Expression(&'a Expression),
+    String(&'a TokenReference),
+    Table(&'a TableConstructor),
+}
+
+impl Argument<'_> {
+    fn start_position(&self) -> Option<full_moon::tokenizer::Position> {
+        match self {
+            Argument::Expression(expr) => expr.start_position(),
+            Argument::String(token) => token.start_position(),
+            Argument::Table(table) => table.start_position(),
+        }
+    }
+
+    fn range(&self) -> Option<Range> {
+        match self {
+            Argument::Expression(expr) => lsp_range(*expr),
+            Argument::String(token) => lsp_range(*token),
+            Argument::Table(table) => lsp_range(*table),
+        }
+    }
+
+    fn expression(&self) -> Option<&Expression> {
+        match self {
+            Argument::Expression(expr) => Some(*expr),
+            _ => None,
+        }
+    }
+
+    /// Whether the argument can expand to any number of values, as calls and `...` do.
+    fn is_multiple(&self) -> bool {
+        match self {
+            Argument::Expression(Expression::Value { value, .. }) => match value.as_ref() {
+                Value::FunctionCall(_) => true,
+                Value::Symbol(token) => matches!(
+                    token.token_type(),
+                    TokenType::Symbol {
+                        symbol: Symbol::Ellipse
+                    }
+                ),
+                _ => false,
+            },
+            _ => false,
+        }
+    }
+
+    /// The table constructor passed, if the argument is one.
+    fn table(&self) -> Option<&TableConstructor> {
+        match self {
+            Argument::Expression(Expression::Value { value, .. }) => match value.as_ref() {
+                Value::TableConstructor(table) => Some(table),
+                _ => None,
+            },
+            Argument::Table(table) => Some(*table),
+            _ => None,
+        }
+    }
+}
+
+/// The arguments passed by a call.
+fn arguments(args: &FunctionArgs) -> Vec<Argument> {
+    match args {
+        FunctionArgs::Parentheses { arguments, .. } => {
+            arguments.iter().map(Argument::Expression).collect()
+        }
+        FunctionArgs::String(token) => vec![Argument::String(token)],
+        FunctionArgs::TableConstructor(table) => vec![Argument::Table(table)],
+        _ => vec![],
+    }
+}
+
+/// The byte range between the parentheses of a parenthesized call, and the byte offsets of the
+/// commas separating its arguments.
+fn argument_list(args: &FunctionArgs) -> Option<(std::ops::Range<usize>, Vec<usize>)> {
+    let FunctionArgs::Parentheses {
+        parentheses,
+        arguments,
+    } = args
+    else {
+        return None;
+    };
+    let (open, close) = parentheses.tokens();
+    let commas = arguments
+        .pairs()
+        .filter_map(|pair| Some(pair.punctuation()?.token().start_position().bytes()))
+        .collect();
+    Some((
+        open.token().end_position().bytes()..close.token().start_position().bytes(),
+        commas,
+    ))
+}
+
+/// Whether `expr` is just a variable named `name`, ignoring ASCII case.
+fn is_named(expr: &Expression, name: &str) -> bool {
+    match expr {
+        Expression::Value { value, .. } => match value.as_ref() {
+            Value::Var(full_moon::ast::Var::Name(var)) => {
+                var.token().to_string().eq_ignore_ascii_case(name)
+            }
+            _ => false,
+        },
+        _ => false,
+    }
+}
+
+/// Whether `expr` is a literal `true` or `false`.
+fn is_boolean_literal(expr: &Expression) -> bool {
+    match expr {
+        Expression::Value { value, .. } => match value.as_ref() {
+            Value::Symbol(token) => matches!(
+                token.token_type(),
+                TokenType::Symbol {
+                    symbol: Symbol::True | Symbol::False
+                }
+            ),
+            _ => false,
+        },
+        _ => false,
+    }
+}
 
 impl ScopeManager {
-    pub fn extract_params(
-        &self,
+    /// The label shown for a parameter or field named `name`, using its configured abbreviation.
+    fn label(&self, name: String) -> String {
+        match self.config.abbreviations.get(&name) {
+            Some(abbreviation) => abbreviation.clone(),
+            None => name,
+        }
+    }
+
+    /// Emits a parameter hint with `label` at `pos`, if it's within the requested range. Hints are
+    /// written with the configured format and padding, and emphasized ones are padded on both
+    /// sides so they stand out. The callee's name, when known,
+    /// is kept in the hint's data.
+    fn push_param_hint(
+        &mut self,
+        label: String,
+        pos: full_moon::tokenizer::Position,
+        emphasize: bool,
+        callee: Option<&str>,
+    ) {
+        let label = self.config.label_format.replace("{name}", &label);
+        self.push_param_label(label, pos, emphasize, callee);
+    }
+
+    /// Emits a parameter hint reading exactly `label`, like [`Self::push_param_hint`] but without
+    /// the configured format.
+    fn push_param_label(
+        &mut self,
+        label: String,
+        pos: full_moon::tokenizer::Position,
+        emphasize: bool,
+        callee: Option<&str>,
+    ) {
+        if !self.in_range(pos) {
+            return;
+        }
+        self.push_hint(InlayHint {
+            position: lsp_types::Position {
+                line: pos.line() as u32,
+                character: pos.character() as u32,
+            },
+            label: InlayHintLabel::String(label),
+            kind: Some(InlayHintKind::PARAMETER),
+            text_edits: None,
+            tooltip: None,
+            padding_left: Some(emphasize || self.config.padding_left),
+            padding_right: Some(emphasize || self.config.padding_right),
+            data: callee.map(|callee| serde_json::json!({ "function": callee })),
+        });
+    }
+
+    /// Warns about a single table argument passed to a function taking several parameters, as in
+    /// `f({ a, b })` for `function f(a, b)`. Functions whose first parameter is annotated as a
+    /// table, like an options table followed by optional parameters, are expected to receive one.
+    fn check_single_table_argument(
+        &mut self,
+        callee: Option<&str>,
+        params: &[Option<(String, full_moon::tokenizer::Position)>],
+        annotations: Option<&Annotations>,
+        arguments: &[Argument],
+    ) {
+        let arity = params
+            .iter()
+            .filter(|slot| slot_name(slot) != "...")
+            .count();
+        if !self.config.warn_single_table_argument || arity < 2 {
+            return;
+        }
+        let first = slot_name(&params[0]);
+        if annotations.is_some_and(|annotations| annotations.is_table(first)) {
+            return;
+        }
+        let [arg] = arguments else {
+            return;
+        };
+        let (Some(_), Some(range)) = (arg.table(), arg.range()) else {
+            return;
+        };
+        self.diagnostics.push(Diagnostic {
+            range,
+            severity: Some(DiagnosticSeverity::WARNING),
+            source: Some("luahint".to_string()),
+            message: format!(
+                "`{}` takes {arity} parameters but is called with a single table",
+                callee.unwrap_or("function")
+            ),
+            ..Default::default()
+        });
+    }
+
+    /// Warns about calls passing more arguments than a function declares, or fewer than it
+    /// requires. Parameters annotated as optional aren't required, and variadic functions are
+    /// never checked.
+    fn check_arity(
+        &mut self,
+        callee: Option<&str>,
+        params: &[Option<(String, full_moon::tokenizer::Position)>],
+        annotations: Option<&Annotations>,
+        arguments: &[Argument],
+        args: &FunctionArgs,
+    ) {
+        if !self.config.check_arity || params.iter().any(|slot| slot_name(slot) == "...") {
+            return;
+        }
+        let name = callee.unwrap_or("function");
+        let arity = params.len();
+        let (range, message) = if arguments.len() > arity {
+            let extra = &arguments[arity..];
+            let (Some(first), Some(last)) = (
+                extra.first().and_then(Argument::range),
+                extra.last().and_then(Argument::range),
+            ) else {
+                return;
+            };
+            (
+                Range {
+                    start: first.start,
+                    end: last.end,
+                },
+                format!(
+                    "`{name}` takes {} but is called with {}",
+                    count_arguments(arity),
+                    arguments.len()
+                ),
+            )
+        } else {
+            let required = params
+                .iter()
+                .rposition(|slot| {
+                    !annotations.is_some_and(|annotations| annotations.is_optional(slot_name(slot)))
+                })
+                .map_or(0, |i| i + 1);
+            // A trailing call or `...` can supply any number of values.
+            if arguments.len() >= required || arguments.last().is_some_and(Argument::is_multiple) {
+                return;
+            }
+            let Some(range) = lsp_range(args) else {
+                return;
+            };
+            (
+                range,
+                format!(
+                    "`{name}` requires {} but is called with {}",
+                    count_arguments(required),
+                    arguments.len()
+                ),
+            )
+        };
+        self.diagnostics.push(Diagnostic {
+            range,
+            severity: Some(DiagnosticSeverity::WARNING),
+            source: Some("luahint".to_string()),
+            message,
+            ..Default::default()
+        });
+    }
+
+    /// Stores an assigned value. Assigning another variable, as in `M.handler = f`, makes an alias
+    /// of it so calls through the new name resolve to the same function. Unknown variables are
+    /// skipped, as nothing useful is known about them. Returns the variable it declared, if any.
+    fn assign(&mut self, scope: ScopeId, name: String, value: Value) -> Option<VarId> {
+        if let Value::Var(full_moon::ast::Var::Name(source)) = &value {
+            let (target, var) = self.find_var_scope(&source.token().to_string())?;
+            return self.define_reference(scope, name, target, var);
+        }
+        self.define(scope, name, value)
+    }
+
+    /// Records a fold over the lines of `node`, unless it fits on one line.
+    fn push_fold(&mut self, node: &impl Node) {
+        let Some(range) = lsp_range(node) else {
+            return;
+        };
+        if range.end.line > range.start.line {
+            self.folds.push(FoldingRange {
+                start_line: range.start.line,
+                end_line: range.end.line,
+                ..Default::default()
+            });
+        }
+    }
+
+    /// Records the name `token` as a usage of the local or parameter it resolves to, if any. It is
+    /// a write if an assignment targets it.
+    fn record_usage(&mut self, token: &TokenReference) {
+        let name = token.token().to_string();
+        let Some((scope, _)) = self.find_var_scope(&name) else {
+            return;
+        };
+        let (Some(&var), Some(position)) =
+            (self.locals.get(&(scope, name)), DefPosition::of(token))
+        else {
+            return;
+        };
+        let write = self.assigned.remove(&position.start.bytes());
+        self.push_usage(scope, var, position, write);
+    }
+
+    /// Records the local `name` just declared in `scope` at `position`, so its usages can be found.
+    fn declare_local(&mut self, scope: ScopeId, name: String, position: Option<DefPosition>) {
+        let (Some(var), Some(position)) = (
+            self.scopes
+                .get(scope)
+                .and_then(|scope| scope.get_var_id(&name)),
+            position,
+        ) else {
+            return;
+        };
+        self.scopes[scope].def_positions.insert(var, position);
+        self.push_usage(scope, var, position, true);
+        self.locals.insert((scope, name), var);
+    }
+
+    /// Binds the local `token` to the value of `expr`. Returns false if nothing is known about the
+    /// value, in which case nothing was declared.
+    fn bind_local(
+        &mut self,
+        scope: ScopeId,
+        token: &TokenReference,
         expr: &Expression,
-    ) -> Option<Vec<(String, full_moon::tokenizer::Position)>> {
+        node: &full_moon::ast::LocalAssignment,
+    ) -> bool {
+        let name = name_of(token);
+        if self.bind_conditional_require(scope, &name, expr) {
+            return true;
+        }
+        let Some(value) = assigned_value(expr) else {
+            return false;
+        };
+        if self.bind_require(scope, &name, value) || self.bind_setmetatable(scope, &name, value) {
+            return true;
+        }
+        if self.assign(scope, name.clone(), value.clone()).is_none() {
+            return false;
+        }
+        if let Value::FunctionCall(call) = value {
+            self.hint_return_kind(token, call);
+        }
+        if let Value::Function(_) = value {
+            self.annotate(
+                scope,
+                &name,
+                Annotations::parse(node.local_token().leading_trivia()),
+            );
+        }
+        true
+    }
+
+    fn push_usage(&mut self, scope: ScopeId, var: VarId, position: DefPosition, write: bool) {
+        self.usages.push(Usage {
+            scope,
+            var,
+            bytes: position.bytes(),
+            range: position.range(),
+            write,
+        });
+    }
+
+    /// The module a `require("mod")` or `require("mod").field` value loads, and the variables
+    /// binding it to `name` defines: `name` itself and the module's exports under it as dotted
+    /// names. Returns `None` if `value` isn't a require of a module that could be loaded.
+    fn require_bindings(&self, name: &str, value: &Value) -> Option<(String, Vec<RequireBinding>)> {
+        let (module, path) = require_target(value)?;
+        let exports = self.module_exports(&module)?;
+        let mut bindings = vec![];
+        let prefix = if path.is_empty() {
+            bindings.push(RequireBinding {
+                name: name.to_string(),
+                export: String::new(),
+                value: Value::TableConstructor(TableConstructor::new()),
+            });
+            String::new()
+        } else {
+            let path = path.join(".");
+            if let Some(value) = exports.get(&path) {
+                bindings.push(RequireBinding {
+                    name: name.to_string(),
+                    export: path.clone(),
+                    value: value.clone(),
+                });
+            }
+            format!("{path}.")
+        };
+        for (key, value) in exports.iter() {
+            let name = match key.strip_prefix(&prefix) {
+                // Methods are exported as `:method`.
+                Some(method) if method.starts_with(':') => format!("{name}{method}"),
+                Some(field) => format!("{name}.{field}"),
+                None => continue,
+            };
+            bindings.push(RequireBinding {
+                name,
+                export: key.clone(),
+                value: value.clone(),
+            });
+        }
+        Some((module, bindings))
+    }
+
+    /// Defines the variables binding a required `module` in `scope`, remembering which of its
+    /// exports each one holds.
+    fn define_required(&mut self, scope: ScopeId, module: &str, bindings: Vec<RequireBinding>) {
+        for binding in bindings {
+            if let Some(var) = self.define(scope, binding.name, binding.value) {
+                self.required
+                    .insert((scope, var), (module.to_string(), binding.export));
+            }
+        }
+    }
+
+    /// Binds a `require("mod")` or `require("mod").field` value to `name` in `scope`, copying the
+    /// module's exports in as dotted names. Returns false if `value` isn't a require of a module
+    /// that could be loaded.
+    fn bind_require(&mut self, scope: ScopeId, name: &str, value: &Value) -> bool {
+        let Some((module, bindings)) = self.require_bindings(name, value) else {
+            return false;
+        };
+        self.define_required(scope, &module, bindings);
+        true
+    }
+
+    /// Binds the optional dependency idiom `ok and require("a") or require("b")` to `name` in
+    /// `scope`. The primary module `a` is the one resolved. Where the fallback `b` can be loaded
+    /// too and disagrees about the shape of an export, that export is left unbound rather than
+    /// risking wrong hints. Returns false if `expr` isn't this idiom or `a` can't be loaded.
+    fn bind_conditional_require(&mut self, scope: ScopeId, name: &str, expr: &Expression) -> bool {
+        let Some((primary, fallback)) = conditional_require(expr) else {
+            return false;
+        };
+        let Some((module, mut bindings)) = self.require_bindings(name, primary) else {
+            return false;
+        };
+        if let Some((_, fallback)) = self.require_bindings(name, fallback) {
+            let fallback = fallback
+                .into_iter()
+                .map(|binding| (binding.name, binding.value))
+                .collect::<HashMap<_, _>>();
+            bindings.retain(|binding| match fallback.get(&binding.name) {
+                Some(other) => same_shape(&binding.value, other),
+                None => true,
+            });
+        }
+        self.define_required(scope, &module, bindings);
+        true
+    }
+
+    /// With `inferMetatables`, binds `setmetatable(t, mt)` to `name` in `scope` as the table `t`,
+    /// and stores a `__call` function in an inline `mt` as `name.__call`, so calls to the table
+    /// resolve to it. Returns false if `value` isn't a call to `setmetatable` or the setting is off,
+    /// in which case the call is assigned like any other value.
+    fn bind_setmetatable(&mut self, scope: ScopeId, name: &str, value: &Value) -> bool {
+        if !self.config.infer_metatables {
+            return false;
+        }
+        let Value::FunctionCall(call) = value else {
+            return false;
+        };
+        match call.prefix() {
+            Prefix::Name(callee) if callee.token().to_string() == "setmetatable" => {}
+            _ => return false,
+        }
+        let mut suffixes = call.suffixes();
+        let Some(Suffix::Call(Call::AnonymousCall(FunctionArgs::Parentheses {
+            arguments, ..
+        }))) = suffixes.next()
+        else {
+            return false;
+        };
+        if suffixes.next().is_some() {
+            return false;
+        }
+        let mut args = arguments.iter();
+        let (Some(table), Some(metatable)) = (args.next(), args.next()) else {
+            return false;
+        };
+
+        let table = match assigned_value(table) {
+            Some(Value::TableConstructor(table)) => table.clone(),
+            _ => TableConstructor::new(),
+        };
+        self.define(scope, name.to_string(), Value::TableConstructor(table));
+        let call = match assigned_value(metatable) {
+            Some(Value::TableConstructor(metatable)) => metatable.named_field("__call".to_string()),
+            _ => None,
+        };
+        if let Some(function) = call
+            .and_then(assigned_value)
+            .filter(|value| matches!(value, Value::Function(_)))
+        {
+            self.define(scope, format!("{name}.__call"), function.clone());
+        }
+        true
+    }
+
+    /// Declares the main chunk's plain global functions up front. By the time anything calls them
+    /// they usually exist, even when the call comes earlier in the file, e.g. in the body of
+    /// another function.
+    pub fn hoist_global_functions(&mut self) {
+        let Some(global_id) = self.stack.first().copied() else {
+            return;
+        };
+        let functions = self
+            .ast
+            .nodes()
+            .stmts()
+            .filter_map(|stmt| match stmt {
+                Stmt::FunctionDeclaration(node)
+                    if node.name().names().len() == 1 && node.name().method_name().is_none() =>
+                {
+                    let name = node.name().names().iter().next()?.token().to_string();
+                    let body = node.body();
+                    let value = Value::Function((body.end_token().clone(), body.clone()));
+                    let annotations = Annotations::parse(node.function_token().leading_trivia());
+                    Some((name, value, annotations, node.name()))
+                }
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        for (name, value, annotations, node) in functions {
+            self.define(global_id, name.clone(), value);
+            self.annotate(global_id, &name, annotations);
+            self.place(global_id, &name, node);
+        }
+    }
+
+    /// Whether calls to `f` may be hinted under `hintExportedOnly`.
+    fn is_exported(&self, f: &FunctionBody) -> bool {
+        let Some(exported) = &self.exported else {
+            return true;
+        };
+        f.end_token()
+            .start_position()
+            .is_some_and(|pos| exported.contains(&pos.bytes()))
+    }
+
+    /// The parameters and annotations of the function a callee name resolves to. Methods declared
+    /// as `function t:m()` get their implicit `self` as the first parameter, so they line up with
+    /// calls like `t.m(t, x)`. Method calls skip it like any other receiver parameter.
+    fn function_params(&self, name: &str) -> Option<(Parameters, Option<Annotations>)> {
+        if name.contains(':') {
+            let (mut params, annotations) = self.declared_params(name)?;
+            params.insert(0, Some(("self".to_string(), Default::default())));
+            return Some((params, annotations));
+        }
+        if let Some(found) = self.declared_params(name) {
+            return Some(found);
+        }
+        let (base, method) = name.rsplit_once('.')?;
+        self.function_params(&format!("{base}:{method}"))
+    }
+
+    /// The parameters and annotations of the function a callee name resolves to, as declared.
+    /// Calling a table invokes its `__call` metamethod, which receives the table itself as the
+    /// first argument.
+    fn declared_params(&self, name: &str) -> Option<(Parameters, Option<Annotations>)> {
+        let Some((scope, val)) = self.resolve_function(name) else {
+            let f = self.field_function(name)?;
+            if !self.is_exported(f) {
+                return None;
+            }
+            return Some((parameters(f), None));
+        };
+        let (scope, val, skip) = match self.get_value(scope, val)? {
+            Value::TableConstructor(_) if self.config.infer_metatables => {
+                let (scope, val) = self.resolve_function(&format!("{name}.__call"))?;
+                (scope, val, 1)
+            }
+            _ => (scope, val, 0),
+        };
+        let Value::Function((_, f)) = self.get_value(scope, val)? else {
+            return None;
+        };
+        if !self.is_exported(f) {
+            return None;
+        }
+        let params = parameters(f).into_iter().skip(skip).collect();
+        Some((params, self.get_annotations(scope, val).cloned()))
+    }
+
+    /// The function a callee name resolves to.
+    fn function_body(&self, name: &str) -> Option<&FunctionBody> {
+        match self.resolve_function(name) {
+            Some((scope, val)) => match self.get_value(scope, val)? {
+                Value::Function((_, f)) => Some(f),
+                _ => None,
+            },
+            None => self.field_function(name),
+        }
+    }
+
+    /// Emits a hint after the local `name` with the kind of value the function called by `call`
+    /// returns, as in `local t: table = make()`.
+    fn hint_return_kind(&mut self, name: &TokenReference, call: &FunctionCall) {
+        if !self.config.show_return_kinds {
+            return;
+        }
+        let Some((callee, _)) = dotted_call(call) else {
+            return;
+        };
+        let (Some(kind), Some(pos)) = (
+            self.function_body(&callee).and_then(return_kind),
+            name.end_position(),
+        ) else {
+            return;
+        };
+        if !self.in_range(pos) {
+            return;
+        }
+        self.push_hint(InlayHint {
+            position: lsp_types::Position {
+                line: pos.line() as u32,
+                character: pos.character() as u32,
+            },
+            label: InlayHintLabel::String(format!(": {kind}")),
+            kind: Some(InlayHintKind::TYPE),
+            text_edits: None,
+            tooltip: None,
+            padding_left: None,
+            padding_right: None,
+            data: None,
+        });
+    }
+
+    /// The function stored in the fields of a table constructor, as `t.a.b` is for
+    /// `local t = { a = { b = function() end } }`. The longest prefix of the name with a known
+    /// value is looked up first, then each remaining segment in the table the previous one
+    /// resolved to.
+    fn field_function(&self, name: &str) -> Option<&FunctionBody> {
+        let mut segments = name.split('.').collect::<Vec<_>>();
+        let mut fields = vec![];
+        let mut value = loop {
+            if let Some((scope, val)) = self.resolve_function(&segments.join(".")) {
+                break self.get_value(scope, val)?;
+            }
+            fields.push(segments.pop()?);
+            if segments.is_empty() {
+                return None;
+            }
+        };
+        for field in fields.into_iter().rev() {
+            let Value::TableConstructor(table) = value else {
+                return None;
+            };
+            let Expression::Value { value: field, .. } = table.named_field(field.to_string())?
+            else {
+                return None;
+            };
+            value = match field.as_ref() {
+                // A field holding a variable, as in `{ handler = handler }`.
+                Value::Var(full_moon::ast::Var::Name(name)) => {
+                    let (scope, val) = self.resolve_function(&name.token().to_string())?;
+                    self.get_value(scope, val)?
+                }
+                field => field,
+            };
+        }
+        match value {
+            Value::Function((_, f)) => Some(f),
+            _ => None,
+        }
+    }
+
+    /// The name, parameters and annotations of the method called as `receiver:method(...)`,
+    /// without the implicit `self`. Methods are found whether they were declared with a colon,
+    /// or with a dot and an explicit `self` parameter.
+    fn method_params(
+        &self,
+        receiver: &Receiver,
+        method: &str,
+    ) -> Option<(String, Parameters, Option<Annotations>)> {
+        match receiver {
+            Receiver::Name(name) => {
+                let colon = format!("{name}:{method}");
+                let (params, annotations) = self
+                    .function_params(&colon)
+                    .or_else(|| self.function_params(&format!("{name}.{method}")))?;
+                Some((colon, params.into_iter().skip(1).collect(), annotations))
+            }
+            // Another module's functions are never among this document's exports.
+            Receiver::Require(..) if self.exported.is_some() => None,
+            Receiver::Require(module, path) => {
+                let exports = self.module_exports(module)?;
+                let path = path.join(".");
+                let (colon, dot) = if path.is_empty() {
+                    (format!(":{method}"), method.to_string())
+                } else {
+                    (format!("{path}:{method}"), format!("{path}.{method}"))
+                };
+                let (f, skip) = match (exports.get(&colon), exports.get(&dot)) {
+                    (Some(Value::Function((_, f))), _) => (f, 0),
+                    (_, Some(Value::Function((_, f)))) => (f, 1),
+                    _ => return None,
+                };
+                let params = parameters(f).into_iter().skip(skip).collect();
+                Some((format!("{module}:{method}"), params, None))
+            }
+        }
+    }
+
+    /// Resolves a callee name to the value it refers to. Variables visible from the current scope
+    /// are always preferred, so user definitions shadow anything built in.
+    pub fn resolve_function(&self, name: &str) -> Option<(ScopeId, ValueId)> {
+        // `_G.name` always refers to the global, even where a local shadows it.
+        if let Some(global) = name.strip_prefix("_G.") {
+            let (scope, var) = self.find_var_from(self.stack.first().copied()?, global)?;
+            return self.resolve_reference(scope, var);
+        }
+        let current = self.get_current_scope_id()?;
+        match self.find_var(name)? {
+            Var::Local(val) => Some((current, val)),
+            Var::Reference(scope, var) => self.resolve_reference(scope, var),
+            Var::Parameter | Var::Unknown => None,
+        }
+    }
+
+    /// Where the function called by `name` is defined, following aliases to it.
+    fn function_definition(&self, name: &str) -> Option<Range> {
+        let (scope, var) = match name.strip_prefix("_G.") {
+            Some(global) => self.find_var_from(self.stack.first().copied()?, global)?,
+            None => self.find_var_scope(name)?,
+        };
+        let (scope, var) = self.resolve_var(scope, var)?;
+        Some(self.def_position(scope, var)?.range())
+    }
+
+    /// The required module and the key of its export that the variable `name` is bound to.
+    fn required_export(&self, name: &str) -> Option<(String, String)> {
+        let (scope, var) = self.find_var_scope(name)?;
+        self.required.get(&(scope, var)).cloned()
+    }
+
+    /// Resolves an assignment target like `t.a.b` to its dotted path and the scope declaring `t`,
+    /// where the field is registered. Targets whose base is unknown or a parameter are ignored,
+    /// since nothing useful is known about the table.
+    pub fn resolve_field_target(&self, var: &VarExpression) -> Option<(ScopeId, String)> {
+        let Prefix::Name(base) = var.prefix() else {
+            return None;
+        };
+        let mut path = base.token().to_string();
+        for suffix in var.suffixes() {
+            match suffix {
+                Suffix::Index(Index::Dot { name, .. }) => {
+                    path.push('.');
+                    path.push_str(&name.token().to_string());
+                }
+                _ => return None,
+            }
+        }
+        let (scope, var) = self.find_var_scope(&base.token().to_string())?;
+        match self.scopes.get(scope)?.var_arena.get(var)? {
+            Var::Parameter | Var::Unknown => None,
+            _ => Some((scope, path)),
+        }
+    }
+
+    pub fn extract_params(&self, expr: &Expression) -> Option<Parameters> {
         match expr {
-            Expression::Value { value } => match value.as_ref() {
-                Value::Function((_, f)) => Some(
-                    f.parameters()
-                        .iter()
-                        .map(|p| {
-                            (
-                                p.to_string().trim().to_string(),
-                                p.start_position().unwrap_or_default(),
-                            )
-                        })
-                        .collect::<Vec<_>>(),
-                ),
+            Expression::Value { value, .. } => match value.as_ref() {
+                Value::Function((_, f)) => Some(parameters(f)),
                 Value::ParenthesesExpression(expr) => self.extract_params(expr),
+                // A call to a function returning a function, as in `(make())(a, b)`.
+                Value::FunctionCall(call) => {
+                    let (name, _) = dotted_call(call)?;
+                    let f = returned_function(self.function_body(&name)?)?;
+                    Some(parameters(f))
+                }
                 Value::Var(var) => match var {
-                    // full_moon::ast::Var::Expression(e) => ,
+                    // A function stored in nested tables, as in `(config.db.query)(sql)`. Each
+                    // segment is looked up in the table before it, so a segment that isn't a table
+                    // ends the lookup.
+                    full_moon::ast::Var::Expression(var) => {
+                        let (params, _) = self.function_params(&dotted_var(var)?)?;
+                        Some(params)
+                    }
                     full_moon::ast::Var::Name(t) => {
-                        let name = t.to_string().trim().to_string();
-                        self.find_var(&name).and_then(|var| match var {
-                            Var::Local(val_id) => self
-                                .get_current_scope()?
-                                .value_arena
-                                .get(val_id)
-                                .and_then(|val| match val {
-                                    Value::Function((_, f)) => Some(
-                                        f.parameters()
-                                            .iter()
-                                            .map(|p| {
-                                                (
-                                                    p.to_string().trim().to_string(),
-                                                    p.start_position().unwrap_or_default(),
-                                                )
-                                            })
-                                            .collect::<Vec<_>>(),
-                                    ),
-                                    _ => None,
-                                }),
-                            Var::Reference(scope_id, var_id) => {
-                                let (scope, val) = self.resolve_reference(scope_id, var_id)?;
-                                self.get_scope(scope)?.value_arena.get(val).and_then(
-                                    |val| match val {
-                                        Value::Function((_, f)) => Some(
-                                            f.parameters()
-                                                .iter()
-                                                .map(|p| {
-                                                    (
-                                                        p.to_string().trim().to_string(),
-                                                        p.start_position().unwrap_or_default(),
-                                                    )
-                                                })
-                                                .collect::<Vec<_>>(),
-                                        ),
-                                        _ => None,
-                                    },
-                                )
-                            }
-                        })
+                        let name = name_of(t);
+                        let (scope, val) = self.resolve_function(&name)?;
+                        match self.get_value(scope, val)? {
+                            Value::Function((_, f)) => Some(parameters(f)),
+                            _ => None,
+                        }
                     }
                     _ => return None,
                 },
@@ -98,7 +1261,7 @@ impl Fields for TableConstructor {
     fn named_field(&self, name: String) -> Option<&Expression> {
         self.fields().into_iter().find_map(|field| match field {
             full_moon::ast::Field::NameKey { key, value, .. } => {
-                (key.to_string() == name).then(|| value)
+                (key.token().to_string() == name).then_some(value)
             }
             _ => None,
         })
@@ -108,7 +1271,9 @@ impl Fields for TableConstructor {
         self.fields()
             .into_iter()
             .filter_map(|field| match field {
-                full_moon::ast::Field::NameKey { key, value, .. } => Some((key.to_string(), value)),
+                full_moon::ast::Field::NameKey { key, value, .. } => {
+                    Some((key.token().to_string(), value))
+                }
                 _ => None,
             })
             .collect()
@@ -156,164 +1321,414 @@ impl Fields for TableConstructor {
 
 impl Visitor for ScopeManager {
     fn visit_block(&mut self, block: &full_moon::ast::Block) {
-        self.open_scope(block);
+        let scope = self.open_scope(block);
+        self.chunk.get_or_insert(scope);
+        if let Some(params) = self.pending_params.take() {
+            for (name, position) in params {
+                self.declare_parameter(scope, name.clone());
+                self.declare_local(scope, name, Some(position));
+            }
+        }
+    }
+
+    fn visit_function_body(&mut self, body: &FunctionBody) {
+        self.push_fold(body);
+        // The body's block is the next block visited, so its scope receives the parameters.
+        self.pending_params = Some(
+            body.parameters()
+                .iter()
+                .filter_map(|param| {
+                    let name = parameter_name(param)?;
+                    is_identifier(&name).then_some((name, DefPosition::of(param)?))
+                })
+                .collect(),
+        );
+    }
+
+    fn visit_var(&mut self, node: &full_moon::ast::Var) {
+        if let full_moon::ast::Var::Name(token) = node {
+            self.record_usage(token);
+        }
+    }
+
+    fn visit_prefix(&mut self, node: &Prefix) {
+        if let Prefix::Name(token) = node {
+            self.record_usage(token);
+        }
     }
 
     fn visit_block_end(&mut self, _node: &full_moon::ast::Block) {
         self.close_scope();
     }
 
+    fn visit_stmt(&mut self, _node: &Stmt) {
+        self.stmt_depths
+            .push((self.stack.len(), self.name_stack.len()));
+    }
+
+    fn visit_stmt_end(&mut self, _node: &Stmt) {
+        // Every scope a statement opens is closed within it, and every function it names has its
+        // body visited within it, otherwise later lookups and scope names go to the wrong scope.
+        let depths = self.stmt_depths.pop();
+        debug_assert_eq!(
+            depths,
+            Some((self.stack.len(), self.name_stack.len())),
+            "unbalanced scope stack after statement"
+        );
+    }
+
     fn visit_local_function(&mut self, func: &full_moon::ast::LocalFunction) {
-        let Some(scope) = self.get_current_scope_mut() else {
+        let Some(scope) = self.get_current_scope_id() else {
 			return
 		};
-        let name = func.name().to_string();
+        let name = name_of(func.name());
         let body = func.body();
-        scope.alloc_local(
+        self.define(
+            scope,
             name.clone(),
             Value::Function((body.end_token().clone(), body.clone())),
         );
+        self.annotate(
+            scope,
+            &name,
+            Annotations::parse(func.local_token().leading_trivia()),
+        );
+        self.declare_local(scope, name.clone(), DefPosition::of(func.name()));
         self.name_next_scope(name);
     }
 
     fn visit_function_declaration(&mut self, node: &FunctionDeclaration) {
-        let Some(global_id) = self.stack.first() else {
-			return
-		};
-        let Some(scope) = self.scopes.get_mut(*global_id) else {
+        let Some(global_id) = self.stack.first().copied() else {
 			return
 		};
-        let name = node.name().to_string().trim().to_string();
+        // Keys are built from the name's tokens so they match the dotted names calls resolve.
+        let mut name = node
+            .name()
+            .names()
+            .iter()
+            .map(|name| name.token().to_string())
+            .collect::<Vec<_>>()
+            .join(".");
+        if let Some(method) = node.name().method_name() {
+            name.push(':');
+            name.push_str(&method.token().to_string());
+        }
         let body = node.body();
-        scope.alloc_local(
+        // Like an assignment, this stores into the variable the base name refers to, so dotted
+        // names like `M.f` end up next to `M`. Unknown names become globals.
+        let owner = node
+            .name()
+            .names()
+            .iter()
+            .next()
+            .and_then(|base| self.find_var_scope(&base.token().to_string()))
+            .map_or(global_id, |(scope, _)| scope);
+        self.define(
+            owner,
             name.clone(),
             Value::Function((body.end_token().clone(), body.clone())),
         );
-        self.stack.push(*global_id);
+        self.annotate(
+            owner,
+            &name,
+            Annotations::parse(node.function_token().leading_trivia()),
+        );
+        self.place(owner, &name, node.name());
         self.name_next_scope(name);
     }
 
-    fn visit_function_declaration_end(&mut self, _node: &FunctionDeclaration) {
-        self.stack.pop();
-    }
-
     fn visit_assignment(&mut self, node: &full_moon::ast::Assignment) {
         let Some(global_id) = self.stack.first().copied() else {
 			return
 		};
 
-        self.stack.push(global_id);
-        node.variables()
-            .into_iter()
-            .collect::<Vec<_>>()
-            .into_iter()
-            .rev()
-            .zip(node.expressions().into_iter())
-            .for_each(|(v, e)| match v {
-                full_moon::ast::Var::Name(name) => match e {
-                    Expression::Value { value } => {
-                        let Some(scope) = self.scopes.get_mut(global_id) else {
-                			return
-                		};
-                        let name = name.to_string().trim().to_string();
-                        scope.alloc_local(name.clone(), *value.clone());
-                        self.name_next_scope(name);
+        // Names are marked before their `Var` nodes are visited, so their usages are writes.
+        for var in node.variables() {
+            if let full_moon::ast::Var::Name(name) = var {
+                if let Some(start) = name.start_position() {
+                    self.assigned.insert(start.bytes());
+                }
+            }
+        }
+
+        // Each target is paired with the expression in the same position. Names rebind the
+        // variable they refer to (e.g. a parameter), and otherwise become globals. Fields are
+        // registered as dotted names in the scope of their table.
+        let targets = node
+            .variables()
+            .iter()
+            .zip(node.expressions().iter())
+            .filter_map(|(v, e)| {
+                let value = assigned_value(e)?.clone();
+                match v {
+                    full_moon::ast::Var::Name(name) => {
+                        let name = name_of(name);
+                        let scope = self
+                            .find_var_scope(&name)
+                            .map_or(global_id, |(scope, _)| scope);
+                        Some((scope, name, value, v, true))
                     }
-                    _ => {}
-                },
-                _ => {}
-            });
-    }
+                    full_moon::ast::Var::Expression(var) => {
+                        let (scope, path) = self.resolve_field_target(var)?;
+                        Some((scope, path, value, v, false))
+                    }
+                    _ => None,
+                }
+            })
+            .collect::<Vec<_>>();
 
-    fn visit_assignment_end(&mut self, _node: &full_moon::ast::Assignment) {
-        self.close_scope();
+        let mut functions = vec![];
+        for (scope, name, value, target, is_name) in targets {
+            if is_name && self.bind_setmetatable(scope, &name, &value) {
+                self.place(scope, &name, target);
+                continue;
+            }
+            let is_function = matches!(value, Value::Function(_));
+            if self.assign(scope, name.clone(), value).is_some() {
+                self.place(scope, &name, target);
+            }
+            if is_function {
+                functions.push(name);
+            }
+        }
+        // Scope names are popped as function bodies are opened, so the first function's name has
+        // to be on top.
+        for name in functions.into_iter().rev() {
+            self.name_next_scope(name);
+        }
     }
 
     fn visit_local_assignment(&mut self, node: &full_moon::ast::LocalAssignment) {
-        node.names()
-            .into_iter()
-            .zip(node.expressions().into_iter())
-            .for_each(|(name, e)| match e {
-                Expression::Value { value } => {
-                    let Some(scope) = self.get_current_scope_mut() else {
-                			return
-                		};
-                    let name = name.to_string().trim().to_string();
-                    scope.alloc_local(name.clone(), *value.clone());
-                    self.name_next_scope(name);
-                }
-                _ => {}
-            });
+        // Function values are named after their local, so the names are pushed before the values
+        // are visited. As for assignments, the first function's name has to be on top.
+        let functions = node
+            .names()
+            .iter()
+            .zip(node.expressions().iter())
+            .filter(|(_, e)| matches!(assigned_value(e), Some(Value::Function(_))))
+            .map(|(token, _)| name_of(token))
+            .collect::<Vec<_>>();
+        for name in functions.into_iter().rev() {
+            self.name_next_scope(name);
+        }
     }
 
-    fn visit_local_assignment_end(&mut self, _node: &full_moon::ast::LocalAssignment) {}
+    fn visit_local_assignment_end(&mut self, node: &full_moon::ast::LocalAssignment) {
+        // Locals only come into scope after their statement, so `local x = x` reads the outer `x`.
+        let Some(scope) = self.get_current_scope_id() else {
+            return;
+        };
+        let mut expressions = node.expressions().iter();
+        for token in node.names() {
+            let name = name_of(token);
+            let bound = match expressions.next() {
+                Some(expr) => self.bind_local(scope, token, expr, node),
+                None => false,
+            };
+            if !bound {
+                self.declare_unknown(scope, name.clone());
+            }
+            self.declare_local(scope, name, DefPosition::of(token));
+        }
+    }
 
-    fn visit_function_call(&mut self, node: &FunctionCall) {
-        let params = match node.prefix() {
-            full_moon::ast::Prefix::Name(n) => {
-                let name = n.to_string().trim().to_string();
-                let curr_scope = self.get_current_scope_id().unwrap();
-
-                let var = self
-                    .find_var(&name)
-                    .and_then(|var| match var {
-                        Var::Local(val) => Some((curr_scope, val)),
-                        Var::Reference(scope, var) => self.resolve_reference(scope, var),
-                    })
-                    .and_then(|(scope, val)| {
-                        let val = self.get_value(scope, val)?;
-                        match val {
-                            Value::Function((_, f)) => Some(f),
-                            _ => None,
-                        }
-                    });
+    fn visit_do(&mut self, node: &full_moon::ast::Do) {
+        self.push_fold(node);
+    }
 
-                if let Some(var) = var {
-                    var.parameters()
-                        .iter()
-                        .map(|p| {
-                            (
-                                p.to_string().trim().to_string(),
-                                p.start_position().unwrap_or_default(),
-                            )
-                        })
-                        .collect::<Vec<_>>()
+    fn visit_while(&mut self, node: &full_moon::ast::While) {
+        self.push_fold(node);
+    }
+
+    fn visit_repeat(&mut self, node: &full_moon::ast::Repeat) {
+        self.push_fold(node);
+    }
+
+    fn visit_numeric_for(&mut self, node: &full_moon::ast::NumericFor) {
+        self.push_fold(node);
+        // Loop variables are declared like parameters, in the scope of the loop's block.
+        let name = node.index_variable().token().to_string();
+        self.pending_params = Some(
+            DefPosition::of(node.index_variable())
+                .map(|position| (name, position))
+                .into_iter()
+                .collect(),
+        );
+    }
+
+    fn visit_generic_for(&mut self, node: &full_moon::ast::GenericFor) {
+        self.push_fold(node);
+        self.pending_params = Some(
+            node.names()
+                .iter()
+                .filter_map(|token| Some((token.token().to_string(), DefPosition::of(token)?)))
+                .collect(),
+        );
+    }
+
+    fn visit_function_call(&mut self, node: &FunctionCall) {
+        let mut annotations = None;
+        let mut callee = None;
+        let mut receiver = None;
+        let (params, args) = match node.prefix() {
+            full_moon::ast::Prefix::Name(_) => {
+                // Method calls come first, as `require("mod"):m()` would otherwise be taken for a
+                // call to `require`.
+                let (name, params, args) = if let Some((target, method, args)) = method_call(node) {
+                    let Some((name, params, method_annotations)) =
+                        self.method_params(&target, &method)
+                    else {
+                        return;
+                    };
+                    if self.config.is_ignored_callee(&name) {
+                        return;
+                    }
+                    annotations = method_annotations;
+                    receiver = node.prefix().start_position();
+                    (name, params, args)
+                } else if let Some((name, args)) = dotted_call(node) {
+                    if self.config.is_ignored_callee(&name) {
+                        return;
+                    }
+                    let Some((params, function_annotations)) = self.function_params(&name) else {
+                        return;
+                    };
+                    annotations = function_annotations;
+                    (name, params, args)
                 } else {
                     return;
+                };
+                if let (Some(start), Some(end)) =
+                    (node.prefix().start_position(), args.start_position())
+                {
+                    let span = start.bytes()..end.bytes();
+                    if let Some(definition) = self.function_definition(&name) {
+                        self.callee_definitions.push((span.clone(), definition));
+                    } else if let Some((module, export)) = self.required_export(&name) {
+                        self.module_callees.push((span.clone(), module, export));
+                    }
+                    self.signatures.push((span, signature(&name, &params)));
+                }
+                if let Some((arguments, commas)) = argument_list(args) {
+                    self.calls.push(CallSite {
+                        signature: signature(&name, &params),
+                        params: params
+                            .iter()
+                            .map(|slot| slot_name(slot).to_string())
+                            .collect(),
+                        arguments,
+                        commas,
+                    });
                 }
+                callee = Some(name);
+                (params, args)
             }
+            // Functions called through an expression are never known to be exported.
+            full_moon::ast::Prefix::Expression(_) if self.exported.is_some() => return,
             full_moon::ast::Prefix::Expression(expr) => {
                 let Some(params) = self.extract_params(expr) else {
 					return;
 				};
-                params
+                let Some(Suffix::Call(Call::AnonymousCall(args))) = node.suffixes().next() else {
+                    return;
+                };
+                (params, args)
             }
             _ => return,
         };
 
-        node.suffixes().next().map(|s| match s {
-            Suffix::Call(Call::AnonymousCall(FunctionArgs::Parentheses { arguments, .. })) => {
-                arguments
-                    .iter()
-                    .zip(params)
-                    .map(|(param, (name, _))| (name, param.start_position().unwrap_or_default()))
-                    .for_each(|(name, pos)| {
-                        self.hints.push(InlayHint {
-                            position: lsp_types::Position {
-                                line: pos.line() as u32,
-                                character: pos.character() as u32,
-                            },
-                            label: InlayHintLabel::String(name),
-                            kind: Some(InlayHintKind::PARAMETER),
-                            text_edits: None,
-                            tooltip: None,
-                            padding_left: None,
-                            padding_right: None,
-                            data: None,
-                        });
-                    });
+        let arguments = arguments(args);
+        self.check_single_table_argument(
+            callee.as_deref(),
+            &params,
+            annotations.as_ref(),
+            &arguments,
+        );
+        self.check_arity(
+            callee.as_deref(),
+            &params,
+            annotations.as_ref(),
+            &arguments,
+            args,
+        );
+
+        let arity = params
+            .iter()
+            .filter(|slot| slot_name(slot) != "...")
+            .count();
+        if arity < self.config.min_params_for_hints
+            || arguments.len() < self.config.min_args_for_hints
+        {
+            return;
+        }
+
+        // The receiver of a method call is passed as the implicit `self`.
+        if let (Some(pos), true) = (receiver, self.config.show_self_hint) {
+            let label = self.label("self".to_string());
+            self.push_param_hint(label, pos, false, callee.as_deref());
+        }
+
+        // Arguments that are all collected by varargs, as for `function log(...)`, have no name to
+        // show.
+        if params.iter().all(|slot| slot_name(slot) == "...") {
+            return;
+        }
+
+        // A trailing `...` is paired with the first argument it collects, which is hinted as `...`
+        // to mark where the variadic arguments begin.
+        for (i, (arg, slot)) in arguments.iter().zip(params).enumerate() {
+            let name = slot.map(|(name, _)| name);
+            if name.as_deref() != Some("...")
+                && self.config.max_parameter_hints.is_some_and(|max| i >= max)
+            {
+                // The marker follows the label of the last hinted argument, the `max`-th.
+                if let (true, Some(last)) = (
+                    self.config.mark_truncated_hints,
+                    i.checked_sub(1).map(|last| &arguments[last]),
+                ) {
+                    let pos = last.start_position().unwrap_or_default();
+                    self.push_param_label("…".to_string(), pos, false, callee.as_deref());
+                }
+                break;
             }
-            _ => {}
-        });
+            // The argument passed for a malformed parameter is left without a hint.
+            let Some(name) = name else {
+                continue;
+            };
+            if name.starts_with('_') && !self.config.hint_underscore_params {
+                continue;
+            }
+            let expr = arg.expression();
+            if self.config.suppress_matching_names && expr.is_some_and(|e| is_named(e, &name)) {
+                continue;
+            }
+            let fields = annotations
+                .as_ref()
+                .and_then(|annotations| annotations.table_fields(&name));
+            let emphasize =
+                self.config.emphasize_boolean_args && expr.is_some_and(is_boolean_literal);
+            let label = match &callee {
+                Some(callee) if self.config.label_include_function => {
+                    format!("{}.{}", short_name(callee), self.label(name))
+                }
+                _ => self.label(name),
+            };
+            self.push_param_hint(
+                label,
+                arg.start_position().unwrap_or_default(),
+                emphasize,
+                callee.as_deref(),
+            );
+
+            // Positional fields of a table passed for an annotated table shape are hinted
+            // with the field names, e.g. `{ width: 10, height: 20 }`.
+            if let (Some(fields), Some(table)) = (fields, arg.table()) {
+                for (field, value) in fields.into_iter().zip(table.indexed_fields()) {
+                    let pos = value.start_position().unwrap_or_default();
+                    let label = self.label(field);
+                    self.push_param_hint(label, pos, false, callee.as_deref());
+                }
+            }
+        }
     }
 }
@@ -0,0 +1,247 @@
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+use dashmap::DashMap;
+use full_moon::ast::Value;
+use lsp_types::{DocumentSymbol, Range};
+
+use crate::config::Config;
+use crate::modules::collect_exports;
+use crate::scope::ScopeManager;
+use crate::symbols::document_symbols;
+use crate::visitor::{lsp_range, parameters, slot_name};
+
+/// Directories that hold dependencies, build output or tooling state rather than the project's
+/// own modules, and are never indexed.
+const SKIPPED_DIRECTORIES: &[&str] = &["node_modules", "lua_modules", ".luarocks", "target"];
+
+/// Indexing stops after this many files, so opening a huge directory can't exhaust memory.
+const MAX_INDEXED_FILES: usize = 10_000;
+
+/// A function exported by a workspace module.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IndexedFunction {
+    pub params: Vec<String>,
+    /// Where the function's name is in its file, or where its body starts if it has no name.
+    pub range: Range,
+}
+
+/// The exported functions of a workspace file, by their key among the module's exports.
+#[derive(Debug, Clone)]
+pub struct IndexedModule {
+    pub path: PathBuf,
+    pub functions: HashMap<String, IndexedFunction>,
+}
+
+/// The modules of the workspace folders, by the name they're `require`d with. Only signatures and
+/// positions are kept, not the files' syntax trees.
+#[derive(Debug, Default)]
+pub struct WorkspaceIndex {
+    modules: DashMap<String, IndexedModule>,
+    /// Set when the server shuts down, which stops any scan in progress.
+    cancelled: AtomicBool,
+}
+
+impl WorkspaceIndex {
+    /// Indexes every Lua file under `roots`, skipping hidden and dependency directories and the
+    /// ones a root's `.luarc.json` lists in `workspace.ignoreDir`.
+    pub fn scan(&self, roots: &[PathBuf]) {
+        let mut files = vec![];
+        let mut scanned = HashSet::new();
+        for root in roots {
+            if scanned.insert(root) {
+                let ignored = ignored_directories(root);
+                collect_files(root, root, &ignored, &mut files);
+            }
+        }
+        for (root, path) in files {
+            if self.cancelled.load(Ordering::Relaxed) {
+                return;
+            }
+            self.index_file(&root, &path);
+        }
+    }
+
+    /// Stops any scan in progress and the ones started later.
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+    }
+
+    /// Indexes the file at `path` under the module name it has relative to `root`.
+    pub fn index_file(&self, root: &Path, path: &Path) {
+        let Some(name) = module_name(root, path) else {
+            return;
+        };
+        let Some(functions) = std::fs::read_to_string(path)
+            .ok()
+            .and_then(|text| index_source(&text))
+        else {
+            return;
+        };
+        self.modules.insert(
+            name,
+            IndexedModule {
+                path: path.to_path_buf(),
+                functions,
+            },
+        );
+    }
+
+    /// Updates the entry of the file at `path` from disk, as after it was saved or changed outside
+    /// the editor. Deleted files are dropped, and an entry is kept as it was while its file doesn't
+    /// parse. Files outside `roots` or in directories a scan skips aren't indexed.
+    pub fn update_file(&self, roots: &[PathBuf], path: &Path) {
+        if !path.is_file() {
+            self.modules.retain(|_, module| module.path != path);
+            return;
+        }
+        let Some(root) = roots.iter().find(|root| path.starts_with(root)) else {
+            return;
+        };
+        let ignored = ignored_directories(root);
+        let skipped = path
+            .ancestors()
+            .skip(1)
+            .take_while(|dir| *dir != root.as_path())
+            .any(|dir| is_skipped(root, dir, &ignored));
+        if !skipped && path.extension().is_some_and(|ext| ext == "lua") {
+            self.index_file(root, path);
+        }
+    }
+
+    /// Drops the modules whose files are under `root`.
+    pub fn remove_root(&self, root: &Path) {
+        self.modules
+            .retain(|_, module| !module.path.starts_with(root));
+    }
+
+    pub fn module(&self, name: &str) -> Option<IndexedModule> {
+        self.modules.get(name).map(|module| module.clone())
+    }
+
+    /// The file of the module `name` and its function exported as `export`.
+    pub fn function(&self, name: &str, export: &str) -> Option<(PathBuf, IndexedFunction)> {
+        let module = self.modules.get(name)?;
+        let function = module.functions.get(export)?.clone();
+        Some((module.path.clone(), function))
+    }
+}
+
+/// The exported functions of a module's source, or `None` if it doesn't parse.
+pub fn index_source(source: &str) -> Option<HashMap<String, IndexedFunction>> {
+    let ast = full_moon::parse(source).ok()?;
+    // Requires aren't followed, so only the module's own functions are indexed.
+    let config = Config {
+        stdlib: false,
+        ..Config::default()
+    };
+    let manager = ScopeManager::new(ast, Arc::new(config), Arc::default()).build();
+    // A function's symbol ends where its body does, which is how exports are matched to names.
+    let mut names = HashMap::new();
+    name_ranges(&document_symbols(manager.ast()), &mut names);
+    let functions = collect_exports(&manager)
+        .into_iter()
+        .filter_map(|(key, value)| {
+            let Value::Function((_, body)) = value else {
+                return None;
+            };
+            let body_range = lsp_range(&body)?;
+            let range = names.get(&body_range.end).copied().unwrap_or(body_range);
+            let params = parameters(&body)
+                .iter()
+                .map(|slot| slot_name(slot).to_string())
+                .collect();
+            Some((key, IndexedFunction { params, range }))
+        })
+        .collect();
+    Some(functions)
+}
+
+/// Collects the name ranges of the functions among `symbols`, by where each function ends.
+fn name_ranges(symbols: &[DocumentSymbol], names: &mut HashMap<lsp_types::Position, Range>) {
+    for symbol in symbols {
+        names.insert(symbol.range.end, symbol.selection_range);
+        name_ranges(symbol.children.as_deref().unwrap_or_default(), names);
+    }
+}
+
+/// The name a file is required with, relative to `root` or to its `lua` directory as in Neovim's
+/// runtime. `init.lua` files are required by their directory's name.
+fn module_name(root: &Path, path: &Path) -> Option<String> {
+    let relative = path.strip_prefix(root).ok()?;
+    let relative = relative.strip_prefix("lua").unwrap_or(relative);
+    let relative = relative.with_extension("");
+    let mut segments = relative
+        .iter()
+        .map(|segment| segment.to_str())
+        .collect::<Option<Vec<_>>>()?;
+    if segments.len() > 1 && segments.last() == Some(&"init") {
+        segments.pop();
+    }
+    Some(segments.join("."))
+}
+
+/// The directories a root's `.luarc.json` excludes from the workspace, relative to the root.
+fn ignored_directories(root: &Path) -> Vec<String> {
+    let Some(luarc) = std::fs::read_to_string(root.join(".luarc.json"))
+        .ok()
+        .and_then(|text| serde_json::from_str::<serde_json::Value>(&text).ok())
+    else {
+        return vec![];
+    };
+    // Both the flat `"workspace.ignoreDir"` key and the nested form are accepted.
+    let entries = luarc
+        .get("workspace.ignoreDir")
+        .or_else(|| luarc.get("workspace")?.get("ignoreDir"))
+        .and_then(|entries| entries.as_array());
+    entries
+        .into_iter()
+        .flatten()
+        .filter_map(|entry| entry.as_str())
+        .map(|entry| entry.trim_end_matches("/**").trim_matches('/').to_string())
+        .filter(|entry| !entry.is_empty())
+        .collect()
+}
+
+/// Whether the directory `dir` under `root` is skipped: hidden, holding dependencies, or listed in
+/// `ignored`.
+fn is_skipped(root: &Path, dir: &Path, ignored: &[String]) -> bool {
+    let Some(name) = dir.file_name().and_then(|name| name.to_str()) else {
+        return false;
+    };
+    let relative = dir.strip_prefix(root).unwrap_or(dir);
+    name.starts_with('.')
+        || SKIPPED_DIRECTORIES.contains(&name)
+        || ignored
+            .iter()
+            .any(|entry| entry == name || relative == Path::new(entry))
+}
+
+/// Adds the Lua files under `dir` to `files`, along with the root they were found from.
+fn collect_files(root: &Path, dir: &Path, ignored: &[String], files: &mut Vec<(PathBuf, PathBuf)>) {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return;
+    };
+    for entry in entries.flatten() {
+        if files.len() >= MAX_INDEXED_FILES {
+            return;
+        }
+        let path = entry.path();
+        // Names that aren't valid UTF-8 can't be part of a module name.
+        if path.file_name().and_then(|name| name.to_str()).is_none() {
+            continue;
+        }
+        let Ok(file_type) = entry.file_type() else {
+            continue;
+        };
+        if file_type.is_dir() {
+            if !is_skipped(root, &path, ignored) {
+                collect_files(root, &path, ignored, files);
+            }
+        } else if file_type.is_file() && path.extension().is_some_and(|ext| ext == "lua") {
+            files.push((root.to_path_buf(), path));
+        }
+    }
+}
@@ -0,0 +1,47 @@
+//! Parsing of the server settings and the warnings about ones that can't be used.
+
+use luahint::config::{Config, Language};
+use serde_json::json;
+
+#[test]
+fn invalid_callee_patterns_are_skipped() {
+    let (config, warnings) =
+        Config::from_value(json!({ "ignoredCalleePatterns": ["^assert", "("] }));
+    assert!(config.is_ignored_callee("assert_eq"));
+    assert!(!config.is_ignored_callee("check"));
+    assert_eq!(warnings.len(), 1);
+    assert!(
+        warnings[0].starts_with("ignoring invalid callee pattern `(`"),
+        "{warnings:?}"
+    );
+}
+
+#[cfg(not(feature = "luau"))]
+#[test]
+fn unsupported_language_warns() {
+    // The setting is kept as is, it doesn't change how documents are parsed.
+    let (config, warnings) = Config::from_value(json!({ "language": "luau" }));
+    assert_eq!(config.language, Some(Language::Luau));
+    assert_eq!(
+        warnings,
+        vec!["luahint was built without luau syntax support, rebuild it with `--features luau`"]
+    );
+}
+
+#[cfg(feature = "luau")]
+#[test]
+fn supported_language_doesnt_warn() {
+    let (config, warnings) = Config::from_value(json!({ "language": "luau" }));
+    assert_eq!(config.language, Some(Language::Luau));
+    assert_eq!(warnings, Vec::<String>::new());
+}
+
+#[test]
+fn label_format_without_name_falls_back() {
+    let (config, warnings) = Config::from_value(json!({ "labelFormat": "param" }));
+    assert_eq!(config.label_format, "{name}:");
+    assert_eq!(
+        warnings,
+        vec!["labelFormat `param` doesn't contain `{name}`, using `{name}:`"]
+    );
+}
@@ -0,0 +1,77 @@
+//! Definitions of the functions called by name.
+
+use std::sync::Arc;
+
+use lsp_types::{Position, Range};
+use luahint::scope::ScopeManager;
+
+/// The range of the definition of the function called at the first occurrence of `call`.
+fn definition(source: &str, call: &str) -> Option<Range> {
+    let ast = full_moon::parse(source).unwrap();
+    let manager = ScopeManager::new(ast, Arc::default(), Arc::default()).build();
+    manager.definition_at(source.find(call).unwrap())
+}
+
+fn range(line: u32, start: u32, end: u32) -> Range {
+    Range {
+        start: Position {
+            line,
+            character: start,
+        },
+        end: Position {
+            line,
+            character: end,
+        },
+    }
+}
+
+#[test]
+fn nearest_definition_wins() {
+    let source = "\
+local function f(a) end
+local function g()
+  local function f(b) end
+  f(1)
+end
+f(2)
+";
+    assert_eq!(definition(source, "f(1)"), Some(range(2, 17, 18)));
+    assert_eq!(definition(source, "f(2)"), Some(range(0, 15, 16)));
+}
+
+#[test]
+fn dotted_function() {
+    let source = "\
+local M = {}
+function M.move(x, y) end
+M.move(1, 2)
+";
+    assert_eq!(definition(source, "M.move(1"), Some(range(1, 9, 15)));
+}
+
+#[test]
+fn aliased_function() {
+    let source = "\
+local function move(x, y) end
+local step = move
+step(1, 2)
+";
+    assert_eq!(definition(source, "step(1"), Some(range(0, 15, 19)));
+}
+
+#[test]
+fn unknown_function() {
+    assert_eq!(definition("print(1)\n", "print"), None);
+}
+
+#[test]
+fn functions_assigned_to_names() {
+    let source = "\
+local f, g = 1, function(a, b) end
+h = function(c, d) end
+g(1, 2)
+h(3, 4)
+";
+    assert_eq!(definition(source, "g(1"), Some(range(0, 9, 10)));
+    assert_eq!(definition(source, "h(3"), Some(range(1, 0, 1)));
+}
@@ -0,0 +1,133 @@
+//! Warnings about how functions are called, with 0-based ranges as published.
+
+use std::sync::Arc;
+
+use luahint::config::Config;
+
+/// The message, line and character range of each diagnostic for `source`.
+fn diagnostics(source: &str) -> Vec<(String, u32, u32, u32)> {
+    let config = Config::from_value(serde_json::json!({ "checkArity": true })).0;
+    luahint::diagnostics_for_source_with(source, Arc::new(config), Arc::default())
+        .unwrap()
+        .into_iter()
+        .map(|diagnostic| {
+            let range = diagnostic.range;
+            (
+                diagnostic.message,
+                range.start.line,
+                range.start.character,
+                range.end.character,
+            )
+        })
+        .collect()
+}
+
+#[test]
+fn too_many_arguments() {
+    let source = "local function f(a, b) end\nf(1, 2, 3, 4)\n";
+    assert_eq!(
+        diagnostics(source),
+        vec![(
+            "`f` takes 2 arguments but is called with 4".to_string(),
+            1,
+            8,
+            12
+        )]
+    );
+}
+
+#[test]
+fn too_few_arguments() {
+    let source = "local function f(a, b) end\nf(1)\n";
+    assert_eq!(
+        diagnostics(source),
+        vec![(
+            "`f` requires 2 arguments but is called with 1".to_string(),
+            1,
+            1,
+            4
+        )]
+    );
+}
+
+#[test]
+fn optional_parameters() {
+    let source = "\
+---@param a number
+---@param b? number
+---@param c string|nil
+local function f(a, b, c) end
+f(1)
+";
+    assert_eq!(diagnostics(source), vec![]);
+}
+
+#[test]
+fn exempt_calls() {
+    let source = "\
+local function f(a, b) end
+local function g(...) end
+local function h() return 1, 2 end
+g(1, 2, 3)
+f(h())
+f(1, ...)
+f(1, 2)
+";
+    assert_eq!(diagnostics(source), vec![]);
+}
+
+#[test]
+fn methods_exclude_self() {
+    let source = "local M = {}\nfunction M:f(a) end\nM:f(1)\nM:f(1, 2)\n";
+    assert_eq!(
+        diagnostics(source),
+        vec![(
+            "`M:f` takes 1 argument but is called with 2".to_string(),
+            3,
+            7,
+            8
+        )]
+    );
+}
+
+#[test]
+fn disabled_by_default() {
+    let source = "local function f(a) end\nf(1, 2)\n";
+    let diagnostics =
+        luahint::diagnostics_for_source_with(source, Arc::default(), Arc::default()).unwrap();
+    assert!(diagnostics.is_empty());
+}
+
+#[test]
+fn zero_arguments() {
+    let source = "\
+local function none() end
+local function one(a) end
+local function two(a, b) end
+none()
+one()
+two()
+";
+    assert_eq!(
+        diagnostics(source),
+        vec![
+            (
+                "`one` requires 1 argument but is called with 0".to_string(),
+                4,
+                3,
+                5
+            ),
+            (
+                "`two` requires 2 arguments but is called with 0".to_string(),
+                5,
+                3,
+                5
+            ),
+        ]
+    );
+}
+
+#[test]
+fn comment_only_document() {
+    assert_eq!(diagnostics("-- just a comment\n"), vec![]);
+}
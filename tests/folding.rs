@@ -0,0 +1,45 @@
+//! Folding ranges of functions and blocks, with 0-based lines.
+
+use std::sync::Arc;
+
+use luahint::scope::ScopeManager;
+
+fn folds(source: &str) -> Vec<(u32, u32)> {
+    let ast = full_moon::parse(source).unwrap();
+    let manager = ScopeManager::new(ast, Arc::default(), Arc::default()).build();
+    manager
+        .folding_ranges()
+        .iter()
+        .map(|fold| (fold.start_line, fold.end_line))
+        .collect()
+}
+
+#[test]
+fn functions_and_blocks() {
+    let source = "\
+local function f(t)
+  for _, v in ipairs(t) do
+    while v do
+      v = nil
+    end
+  end
+  do
+    return
+  end
+end
+local g = function()
+  repeat
+  until true
+end
+";
+    assert_eq!(
+        folds(source),
+        vec![(0, 9), (1, 5), (2, 4), (6, 8), (10, 13), (11, 12)]
+    );
+}
+
+#[test]
+fn single_lines_are_not_folded() {
+    let source = "local function f() return 1 end\ndo local x = f() end\n";
+    assert_eq!(folds(source), vec![]);
+}
@@ -0,0 +1,86 @@
+//! Usages of locals and parameters, as highlighted under the cursor and renamed together.
+
+use std::sync::Arc;
+
+use luahint::scope::ScopeManager;
+
+/// The byte ranges of the usages of the local at `offset`, and whether each is a write.
+fn usages(source: &str, offset: usize) -> Vec<(usize, usize, bool)> {
+    let ast = full_moon::parse(source).unwrap();
+    let manager = ScopeManager::new(ast, Arc::default(), Arc::default()).build();
+    manager
+        .usages_at(offset)
+        .into_iter()
+        .map(|usage| (usage.bytes.start, usage.bytes.end, usage.write))
+        .collect()
+}
+
+#[test]
+fn reads_and_writes() {
+    let source = "\
+local function f(n, m)
+  n = n + m
+  return n
+end
+";
+    assert_eq!(
+        usages(source, 17),
+        vec![
+            (17, 18, true),
+            (25, 26, true),
+            (29, 30, false),
+            (44, 45, false)
+        ]
+    );
+}
+
+#[test]
+fn shadowed_parameter() {
+    let source = "\
+local function f(n)
+  do
+    local n = 1
+    print(n)
+  end
+  print(n)
+end
+";
+    let n = source.rfind("(n)").unwrap() + 1;
+    assert_eq!(usages(source, n), vec![(17, 18, true), (n, n + 1, false)]);
+}
+
+#[test]
+fn shadowing_locals() {
+    let source = "\
+local x = 1
+do
+  local x = x + 1
+  print(x)
+end
+for _, x in pairs(t) do
+  print(x)
+end
+print(x)
+";
+    let inner = source.find("x + 1").unwrap();
+    let last = source.rfind('x').unwrap();
+    assert_eq!(
+        usages(source, 6),
+        vec![
+            (6, 7, true),
+            (inner, inner + 1, false),
+            (last, last + 1, false)
+        ]
+    );
+}
+
+#[test]
+fn locals_are_in_scope_after_their_statement() {
+    let source = "local f = function() return f end\nf()\n";
+    assert_eq!(usages(source, 6), vec![(6, 7, true), (34, 35, false)]);
+}
+
+#[test]
+fn globals_are_not_tracked() {
+    assert_eq!(usages("x = 1\nprint(x)\n", 12), vec![]);
+}
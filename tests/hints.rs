@@ -0,0 +1,838 @@
+//! Snapshots of the hints produced for known snippets. Positions are 1-based, as emitted.
+
+use std::sync::Arc;
+
+use lsp_types::{InlayHint, InlayHintLabel};
+use luahint::config::Config;
+use luahint::scope::ScopeManager;
+
+/// The label, line and character of each hint for `source`, in document order.
+fn hints(source: &str) -> Vec<(String, u32, u32)> {
+    hints_with(source, Config::default())
+}
+
+fn hints_with(source: &str, config: Config) -> Vec<(String, u32, u32)> {
+    let mut hints = raw_hints(source, config)
+        .into_iter()
+        .map(|hint| {
+            let label = match hint.label {
+                InlayHintLabel::String(label) => label,
+                InlayHintLabel::LabelParts(parts) => {
+                    parts.into_iter().map(|part| part.value).collect()
+                }
+            };
+            (label, hint.position.line, hint.position.character)
+        })
+        .collect::<Vec<_>>();
+    hints.sort_by_key(|(_, line, character)| (*line, *character));
+    hints
+}
+
+/// The hints for `source` as the server sends them, in the order they were emitted.
+fn raw_hints(source: &str, config: Config) -> Vec<InlayHint> {
+    luahint::hints_for_source_with(source, Arc::new(config), Arc::default()).unwrap()
+}
+
+/// The left and right padding of each hint for `source`, in the order they were emitted.
+fn paddings(source: &str, config: Config) -> Vec<(Option<bool>, Option<bool>)> {
+    raw_hints(source, config)
+        .into_iter()
+        .map(|hint| (hint.padding_left, hint.padding_right))
+        .collect()
+}
+
+fn hint(label: &str, line: u32, character: u32) -> (String, u32, u32) {
+    (label.to_string(), line, character)
+}
+
+#[test]
+fn local_function() {
+    let source = "local function add(a, b) end\nadd(1, 2)\n";
+    assert_eq!(hints(source), vec![hint("a:", 2, 5), hint("b:", 2, 8)]);
+}
+
+#[test]
+fn global_function() {
+    let source = "function greet(name) end\ngreet(\"world\")\n";
+    assert_eq!(hints(source), vec![hint("name:", 2, 7)]);
+}
+
+#[test]
+fn function_assigned_to_local() {
+    let source = "local scale = function(value, factor) end\nscale(x, 2)\n";
+    assert_eq!(
+        hints(source),
+        vec![hint("value:", 2, 7), hint("factor:", 2, 10)]
+    );
+}
+
+#[test]
+fn nested_function() {
+    let source = "\
+local function outer()
+  local function inner(depth) end
+  inner(1)
+end
+outer()
+";
+    assert_eq!(hints(source), vec![hint("depth:", 3, 9)]);
+}
+
+#[test]
+fn closure_calls_outer_local() {
+    let source = "\
+local function log(msg) end
+local function run()
+  return function() log(\"x\") end
+end
+";
+    assert_eq!(hints(source), vec![hint("msg:", 3, 25)]);
+}
+
+#[test]
+fn varargs() {
+    let source = "local function f(a, ...) end f(1, 2, 3)\n";
+    assert_eq!(hints(source), vec![hint("a:", 1, 32), hint("...:", 1, 35)]);
+}
+
+#[test]
+fn fewer_arguments_than_parameters() {
+    let source = "local function f(a, b, c) end\nf(1)\n";
+    assert_eq!(hints(source), vec![hint("a:", 2, 3)]);
+}
+
+#[test]
+fn multiple_assignment() {
+    let source = "a, b = function(x) end, function(y) end\na(1)\nb(2)\n";
+    assert_eq!(hints(source), vec![hint("x:", 2, 3), hint("y:", 3, 3)]);
+}
+
+#[test]
+fn return_kinds() {
+    let source = "\
+local function make()
+  local t = {}
+  return t
+end
+local function name() return \"x\" end
+local a = make()
+local b = name()
+";
+    let config = Config::from_value(serde_json::json!({ "showReturnKinds": true })).0;
+    assert_eq!(
+        hints_with(source, config),
+        vec![hint(": table", 6, 8), hint(": string", 7, 8)]
+    );
+}
+
+#[test]
+fn call_through_returned_function() {
+    let source = "\
+local function handler()
+  local function handle(req, res) end
+  return handle
+end
+(handler())(1, 2)
+";
+    assert_eq!(
+        hints(source),
+        vec![hint("req:", 5, 13), hint("res:", 5, 16)]
+    );
+}
+
+#[test]
+fn dotted_declaration_in_function() {
+    let source = "\
+local a = { b = {} }
+local function outer()
+  function a.b.c(x) end
+  a.b.c(1)
+end
+a.b.c(2)
+";
+    assert_eq!(hints(source), vec![hint("x:", 4, 9), hint("x:", 6, 7)]);
+}
+
+#[test]
+fn methods_take_self_first() {
+    let source = "\
+local t = {}
+function t.dot(self, x) end
+function t:colon(x) end
+t:dot(1)
+t:colon(2)
+t.dot(t, 3)
+t.colon(t, 4)
+";
+    assert_eq!(
+        hints(source),
+        vec![
+            hint("x:", 4, 7),
+            hint("x:", 5, 9),
+            hint("self:", 6, 7),
+            hint("x:", 6, 10),
+            hint("self:", 7, 9),
+            hint("x:", 7, 12),
+        ]
+    );
+}
+
+#[test]
+fn dispatch_table_fields() {
+    let source = "\
+local handlers = { onClick = function(evt) end }
+handlers.onClick(e)
+handlers[\"onClick\"](e)
+";
+    assert_eq!(
+        hints(source),
+        vec![hint("evt:", 2, 18), hint("evt:", 3, 21)]
+    );
+}
+
+#[test]
+fn max_parameter_hints() {
+    let source = "\
+local function f(a, b, c, ...) end
+f(1, 2, 3, 4)
+local function g(a, ...) end
+g(1, 2, 3)
+";
+    let config = Config::from_value(serde_json::json!({
+        "maxParameterHints": 1,
+        "markTruncatedHints": true,
+    }))
+    .0;
+    assert_eq!(
+        hints_with(source, config),
+        vec![
+            hint("a:", 2, 3),
+            hint("…", 2, 3),
+            hint("a:", 4, 3),
+            hint("...:", 4, 6),
+        ]
+    );
+}
+
+#[test]
+fn goto_and_labels() {
+    let source = "\
+local function f(a) end
+::top::
+f(1)
+goto top
+";
+    assert_eq!(hints(source), vec![hint("a:", 3, 3)]);
+}
+
+#[cfg(feature = "lua54")]
+#[test]
+fn lua54_attributes_and_integer_division() {
+    let source = "\
+local add <const> = function(a, b) end
+add(7 // 2, 1)
+";
+    assert_eq!(hints(source), vec![hint("a:", 2, 5), hint("b:", 2, 13)]);
+}
+
+#[test]
+fn names_exclude_trivia() {
+    let source = "local function f --[[ the function ]] (a --[[ first ]], b) end\nf(1, 2)\n";
+    assert_eq!(hints(source), vec![hint("a:", 2, 3), hint("b:", 2, 6)]);
+}
+
+#[cfg(feature = "luau")]
+#[test]
+fn luau_typed_parameters() {
+    let source = "local function f(x: number, y: string) end\nf(1, \"a\")\n";
+    assert_eq!(hints(source), vec![hint("x:", 2, 3), hint("y:", 2, 6)]);
+}
+
+#[cfg(feature = "lua54")]
+#[test]
+fn lua54_close_local() {
+    let source = "\
+local function f(a) end
+local y <close> = f
+y(1)
+";
+    assert_eq!(hints(source), vec![hint("a:", 3, 3)]);
+}
+
+#[test]
+fn hints_within_range_of_built_manager() {
+    let source = "local function f(a, b) end\nf(1, 2)\nf(3, 4)\nf(5, 6)\n";
+    let ast = full_moon::parse(source).unwrap();
+    let range = lsp_types::Range {
+        start: lsp_types::Position::new(2, 0),
+        end: lsp_types::Position::new(3, 0),
+    };
+    let positions = |hints: Vec<lsp_types::InlayHint>| {
+        hints
+            .into_iter()
+            .map(|hint| (hint.position.line, hint.position.character))
+            .collect::<Vec<_>>()
+    };
+    let manager = ScopeManager::new(ast.clone(), Arc::default(), Arc::default()).build();
+    assert_eq!(positions(manager.hints_within(range)), vec![(3, 3), (3, 6)]);
+    assert_eq!(
+        positions(ScopeManager::hints_in_range(
+            ast,
+            Arc::default(),
+            Arc::default(),
+            range
+        )),
+        vec![(3, 3), (3, 6)]
+    );
+}
+
+#[test]
+fn empty_range_is_the_whole_document() {
+    let source = "local function f(a) end\nf(1)\nf(2)\n";
+    let ast = full_moon::parse(source).unwrap();
+    let manager = ScopeManager::new(ast.clone(), Arc::default(), Arc::default()).build();
+    // Clients asking about the cursor send a zero-width range at it.
+    let cursor = lsp_types::Range {
+        start: lsp_types::Position::new(1, 2),
+        end: lsp_types::Position::new(1, 2),
+    };
+    assert_eq!(manager.hints_within(cursor).len(), 2);
+    assert_eq!(
+        ScopeManager::hints_in_range(ast, Arc::default(), Arc::default(), cursor).len(),
+        2
+    );
+}
+
+#[test]
+fn parenthesized_table_fields() {
+    let source = "\
+local config = { db = { query = function(sql, params) end } }
+local rows = (config.db.query)(\"select\", {})
+local n = 1
+local x = (n.db.query)(\"select\", {})
+local y = (config.missing.query)(\"select\", {})
+";
+    assert_eq!(
+        hints(source),
+        vec![hint("sql:", 2, 32), hint("params:", 2, 42)]
+    );
+}
+
+#[test]
+fn stdlib_functions() {
+    let source = "table.insert(t, 1)\nlocal s = string.rep(\"-\", 3)\n";
+    assert_eq!(
+        hints(source),
+        vec![
+            hint("list:", 1, 14),
+            hint("...:", 1, 17),
+            hint("s:", 2, 22),
+            hint("n:", 2, 27)
+        ]
+    );
+    let config = Config::from_value(serde_json::json!({ "stdlib": false })).0;
+    assert_eq!(hints_with(source, config), vec![]);
+}
+
+#[test]
+fn stdlib_functions_are_shadowed() {
+    let source = "local function pcall(g) end\npcall(print)\n";
+    assert_eq!(hints(source), vec![hint("g:", 2, 7)]);
+}
+
+#[test]
+fn calls_inside_callback_arguments() {
+    let source = "\
+local function foo(value) end
+table.sort(t, function(x, y) foo(x) end)
+";
+    assert_eq!(
+        hints(source),
+        vec![
+            hint("list:", 2, 12),
+            hint("comp:", 2, 15),
+            hint("value:", 2, 34)
+        ]
+    );
+}
+
+#[test]
+fn zero_argument_calls() {
+    let source = "\
+local function none() end
+local function one(a) end
+local function two(a, b) end
+none()
+one()
+two()
+";
+    assert_eq!(hints(source), vec![]);
+}
+
+#[test]
+fn calls_inside_callback_table_fields() {
+    // The callback's own parameter is a definition, only the call in its body is hinted.
+    let source = "\
+local function handle(event) end
+register({ onEvent = function(e) handle(e) end })
+";
+    assert_eq!(hints(source), vec![hint("event:", 2, 41)]);
+}
+
+#[test]
+fn do_block_locals_and_globals() {
+    let source = "\
+function f(global) end
+do
+  local f = function(inner) end
+  f(1)
+  function g(x) end
+  local function h(y) end
+end
+f(2)
+g(3)
+h(4)
+";
+    // Globals declared in the block escape it, its locals don't.
+    assert_eq!(
+        hints(source),
+        vec![
+            hint("inner:", 4, 5),
+            hint("global:", 8, 3),
+            hint("x:", 9, 3)
+        ]
+    );
+}
+
+#[test]
+fn comment_only_document() {
+    let source = "-- Copyright (c) the authors\n--[[ nothing else\nhere ]]\n";
+    assert_eq!(hints(source), vec![]);
+}
+
+#[test]
+fn parameters_named_like_builtins() {
+    // Only the receiver of a method call is the implicit `self`, a parameter named `self` in a
+    // plain function is hinted like any other.
+    let source = "\
+local function f(self, x) end
+f(obj, 1)
+local function g(type, pairs) end
+g(1, 2)
+";
+    assert_eq!(
+        hints(source),
+        vec![
+            hint("self:", 2, 3),
+            hint("x:", 2, 8),
+            hint("type:", 4, 3),
+            hint("pairs:", 4, 6)
+        ]
+    );
+}
+
+#[test]
+fn negative_number_arguments() {
+    // The hint goes before the minus sign, so it reads `a: -5` rather than `-a: 5`.
+    let source = "local function f(a, b) end\nf(-5, -3.2)\n";
+    assert_eq!(hints(source), vec![hint("a:", 2, 3), hint("b:", 2, 7)]);
+}
+
+#[test]
+fn calls_inside_setup_table_functions() {
+    let source = "\
+local function configure(options) end
+setup({ init = function(opts) configure(opts) end })
+";
+    assert_eq!(hints(source), vec![hint("options:", 2, 41)]);
+}
+
+#[test]
+fn parameters_swapped_in_body() {
+    let source = "function f(a, b) a, b = b, a end\nf(b, a)\n";
+    assert_eq!(hints(source), vec![hint("a:", 2, 3), hint("b:", 2, 6)]);
+}
+
+#[test]
+fn functions_resolved_through_stored_values() {
+    // Hints land on the arguments of each call, not at positions taken from the stored body.
+    let source = "\
+local function f(a, b) end
+local g = f
+local t = { h = f }
+g(1, 2)
+t.h(3, 4)
+";
+    assert_eq!(
+        hints(source),
+        vec![
+            hint("a:", 4, 3),
+            hint("b:", 4, 6),
+            hint("a:", 5, 5),
+            hint("b:", 5, 8)
+        ]
+    );
+}
+
+#[test]
+fn setmetatable_module_table() {
+    let source = "\
+local base = {}
+local M = setmetatable({}, { __index = base })
+function M.greet(name) end
+M.greet(\"x\")
+return M
+";
+    assert_eq!(
+        hints(source),
+        vec![
+            hint("table:", 2, 24),
+            hint("metatable:", 2, 28),
+            hint("name:", 4, 9)
+        ]
+    );
+}
+
+#[test]
+fn tab_indentation() {
+    // A tab is one character, whatever width the editor shows it with.
+    let source = "local function f(a, b) end\ndo\n\tf(1,\n\t\t2)\nend\n";
+    assert_eq!(hints(source), vec![hint("a:", 3, 4), hint("b:", 4, 3)]);
+}
+
+#[test]
+fn malformed_parameters_keep_positions() {
+    use full_moon::ast::punctuated::Punctuated;
+    use full_moon::ast::{Parameter, Stmt};
+    use full_moon::tokenizer::TokenReference;
+
+    let source = "local function f(a, b, c) end\nf(1, 2, 3)\n";
+    let ast = full_moon::parse(source).unwrap();
+    let mut stmts = ast
+        .nodes()
+        .stmts_with_semicolon()
+        .cloned()
+        .collect::<Vec<_>>();
+    let (Stmt::LocalFunction(func), _) = &stmts[0] else {
+        panic!("expected a local function");
+    };
+    // As in a partially recovered signature, the second entry isn't a name.
+    let params = func
+        .body()
+        .parameters()
+        .clone()
+        .into_pairs()
+        .enumerate()
+        .map(|(i, pair)| match i {
+            1 => pair.map(|_| Parameter::Name(TokenReference::symbol("+").unwrap())),
+            _ => pair,
+        })
+        .collect::<Punctuated<_>>();
+    let func = func
+        .clone()
+        .with_body(func.body().clone().with_parameters(params));
+    stmts[0].0 = Stmt::LocalFunction(func);
+    let block = ast.nodes().clone().with_stmts(stmts);
+    let ast = ast.with_nodes(block);
+
+    let hints = ScopeManager::new(ast, Arc::default(), Arc::default())
+        .build()
+        .hints_within(lsp_types::Range::default())
+        .into_iter()
+        .map(|hint| match hint.label {
+            InlayHintLabel::String(label) => (label, hint.position.line, hint.position.character),
+            InlayHintLabel::LabelParts(_) => panic!("expected a plain label"),
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(hints, vec![hint("a:", 2, 3), hint("c:", 2, 9)]);
+}
+
+#[test]
+fn vararg_only_function() {
+    let source = "function log(...) end\nlog(a, b, c)\n";
+    assert_eq!(hints(source), vec![]);
+}
+
+#[test]
+fn callable_tables() {
+    let source = "\
+local obj = setmetatable({}, { __call = function(self, x) end })
+obj(5)
+";
+    let config = |infer: bool| {
+        Config::from_value(serde_json::json!({ "stdlib": false, "inferMetatables": infer })).0
+    };
+    assert_eq!(hints_with(source, config(true)), vec![hint("x:", 2, 5)]);
+    assert_eq!(hints_with(source, config(false)), vec![]);
+}
+
+#[test]
+fn self_hints_on_method_receivers() {
+    let source = "local obj = {}\nfunction obj:move(x) end\nobj:move(1)\n";
+    assert_eq!(hints(source), vec![hint("x:", 3, 10)]);
+    let config = Config::from_value(serde_json::json!({ "showSelfHint": true })).0;
+    assert_eq!(
+        hints_with(source, config),
+        vec![hint("self:", 3, 1), hint("x:", 3, 10)]
+    );
+}
+
+#[test]
+fn guarded_calls() {
+    let source = "\
+local cb = function(a) end
+if cb then cb(1) end
+local _ = cb and cb(2)
+";
+    assert_eq!(hints(source), vec![hint("a:", 2, 15), hint("a:", 3, 21)]);
+}
+
+#[test]
+fn functions_assigned_to_parameters() {
+    // The assignment rebinds the parameter, it doesn't declare a global.
+    let source = "\
+local function run(cb)
+  cb = function(value) end
+  if cb then cb(1) end
+end
+cb(2)
+";
+    assert_eq!(hints(source), vec![hint("value:", 3, 17)]);
+}
+
+#[test]
+fn ignored_callee_patterns() {
+    let source = "\
+local function assert_eq(a, b) end
+local function check(value) end
+assert_eq(1, 2)
+check(3)
+";
+    let config = Config::from_value(serde_json::json!({ "ignoredCalleePatterns": ["^assert"] })).0;
+    assert_eq!(hints_with(source, config), vec![hint("value:", 4, 7)]);
+}
+
+#[test]
+fn emphasized_boolean_arguments() {
+    let source = "local function f(a, b, c) end\nf(true, false, 1)\n";
+    let config = Config::from_value(serde_json::json!({ "emphasizeBooleanArgs": true })).0;
+    assert_eq!(
+        paddings(source, config),
+        vec![
+            (Some(true), Some(true)),
+            (Some(true), Some(true)),
+            (Some(false), Some(true))
+        ]
+    );
+    assert_eq!(
+        paddings(source, Config::default()),
+        vec![(Some(false), Some(true)); 3]
+    );
+}
+
+#[test]
+fn min_params_for_hints() {
+    // Varargs don't count as a named parameter.
+    let source = "\
+local function one(a, ...) end
+local function two(a, b) end
+one(1, 2)
+two(1, 2)
+";
+    let config = Config::from_value(serde_json::json!({ "minParamsForHints": 2 })).0;
+    assert_eq!(
+        hints_with(source, config),
+        vec![hint("a:", 4, 5), hint("b:", 4, 8)]
+    );
+}
+
+#[test]
+fn underscore_parameters() {
+    let source = "local function f(_, _foo, bar) end\nf(1, 2, 3)\n";
+    assert_eq!(hints(source), vec![hint("bar:", 2, 9)]);
+    let config = Config::from_value(serde_json::json!({ "hintUnderscoreParams": true })).0;
+    assert_eq!(
+        hints_with(source, config),
+        vec![hint("_:", 2, 3), hint("_foo:", 2, 6), hint("bar:", 2, 9)]
+    );
+}
+
+#[test]
+fn abbreviated_labels() {
+    let source = "local function on(event, callback) end\non(\"x\", f)\n";
+    let config = Config::from_value(serde_json::json!({ "abbreviations": { "callback": "cb" } })).0;
+    assert_eq!(
+        hints_with(source, config),
+        vec![hint("event:", 2, 4), hint("cb:", 2, 9)]
+    );
+}
+
+#[test]
+fn annotated_options_table() {
+    let source = "\
+---@param opts {width: number, height: number}
+local function resize(opts) end
+resize({ 10, 20 })
+";
+    assert_eq!(
+        hints(source),
+        vec![
+            hint("opts:", 3, 8),
+            hint("width:", 3, 10),
+            hint("height:", 3, 14)
+        ]
+    );
+}
+
+#[test]
+fn module_factory_closures() {
+    let source = "\
+return function(config)
+    local function helper(x) end
+    return {
+        run = function()
+            helper(1)
+        end,
+    }
+end
+";
+    assert_eq!(hints(source), vec![hint("x:", 5, 20)]);
+}
+
+#[test]
+fn functions_aliased_into_table_fields() {
+    let source = "\
+local M = {}
+local function f(a) end
+M.handler = f
+M.handler(1)
+M.other = undefined
+M.other(1)
+";
+    assert_eq!(hints(source), vec![hint("a:", 4, 11)]);
+}
+
+#[test]
+fn module_guards() {
+    let source = "M = M or {}\nfunction M.f(a) end\nM.f(1)\n";
+    assert_eq!(hints(source), vec![hint("a:", 3, 5)]);
+    // Without the bundled definitions, so the call to `rawget` isn't hinted itself.
+    let config = Config::from_value(serde_json::json!({ "stdlib": false })).0;
+    let source = "local M = rawget(_G, \"M\") or {}\nfunction M.f(a) end\nM.f(1)\n";
+    assert_eq!(hints_with(source, config), vec![hint("a:", 3, 5)]);
+}
+
+#[test]
+fn stdlib_functions_shadowed_in_a_block() {
+    let source = "\
+do
+  local function tostring(x, y) end
+  tostring(1, 2)
+end
+tostring(3)
+";
+    // Outside the shadowing block, the bundled signature is used again.
+    assert_eq!(
+        hints(source),
+        vec![hint("x:", 3, 12), hint("y:", 3, 15), hint("v:", 5, 10)]
+    );
+}
+
+#[test]
+fn label_format() {
+    let source = "local function add(a, b) end\nadd(1, 2)\n";
+    let config = Config::from_value(serde_json::json!({ "labelFormat": "{name} =" })).0;
+    assert_eq!(
+        hints_with(source, config),
+        vec![hint("a =", 2, 5), hint("b =", 2, 8)]
+    );
+    // A format without `{name}` is replaced by the default one.
+    let config = Config::from_value(serde_json::json!({ "labelFormat": "param" })).0;
+    assert_eq!(
+        hints_with(source, config),
+        vec![hint("a:", 2, 5), hint("b:", 2, 8)]
+    );
+}
+
+#[test]
+fn min_args_for_hints() {
+    let source = "local function f(a, b) end\nf(1)\nf(1, 2)\n";
+    assert_eq!(
+        hints(source),
+        vec![hint("a:", 2, 3), hint("a:", 3, 3), hint("b:", 3, 6)]
+    );
+    // Calls passing exactly the minimum are hinted, calls passing fewer aren't.
+    let config = Config::from_value(serde_json::json!({ "minArgsForHints": 2 })).0;
+    assert_eq!(
+        hints_with(source, config),
+        vec![hint("a:", 3, 3), hint("b:", 3, 6)]
+    );
+}
+
+#[test]
+fn hint_padding() {
+    let source = "local function f(a) end\nf(1)\n";
+    assert_eq!(
+        paddings(source, Config::default()),
+        vec![(Some(false), Some(true))]
+    );
+    let config = Config::from_value(serde_json::json!({
+        "paddingLeft": true,
+        "paddingRight": false,
+    }))
+    .0;
+    assert_eq!(paddings(source, config), vec![(Some(true), Some(false))]);
+}
+
+#[test]
+fn labels_including_the_function() {
+    let source = "\
+local function move(x, y) end
+local M = {}
+function M.move(x) end
+local obj = {}
+function obj:move(dx) end
+move(1, 2)
+M.move(3)
+obj:move(4)
+";
+    assert_eq!(
+        hints(source),
+        vec![
+            hint("x:", 6, 6),
+            hint("y:", 6, 9),
+            hint("x:", 7, 8),
+            hint("dx:", 8, 10)
+        ]
+    );
+    // Functions are named the same way whether they're called directly, through a table or as a
+    // method.
+    let config = Config::from_value(serde_json::json!({ "labelIncludeFunction": true })).0;
+    assert_eq!(
+        hints_with(source, config),
+        vec![
+            hint("move.x:", 6, 6),
+            hint("move.y:", 6, 9),
+            hint("move.x:", 7, 8),
+            hint("move.dx:", 8, 10)
+        ]
+    );
+}
+
+#[test]
+fn suppress_matching_names() {
+    let source = "\
+local function f(x) end
+local x, X, y = 1, 2, 3
+f(x)
+f(X)
+f(y)
+";
+    assert_eq!(
+        hints(source),
+        vec![hint("x:", 3, 3), hint("x:", 4, 3), hint("x:", 5, 3)]
+    );
+    // Arguments named like the parameter, ignoring case, aren't hinted.
+    let config = Config::from_value(serde_json::json!({ "suppressMatchingNames": true })).0;
+    assert_eq!(hints_with(source, config), vec![hint("x:", 5, 3)]);
+}
@@ -0,0 +1,112 @@
+//! Resolution of `require`d modules, including ones open in the editor.
+
+use std::sync::Arc;
+
+use crop::Rope;
+use lsp_types::InlayHintLabel;
+use luahint::modules::Modules;
+use luahint::scope::ScopeManager;
+
+fn labels(source: &str, modules: &Arc<Modules>) -> Vec<String> {
+    luahint::hints_for_source_with(source, Arc::default(), modules.clone())
+        .unwrap()
+        .into_iter()
+        .filter_map(|hint| match hint.label {
+            InlayHintLabel::String(label) => Some(label),
+            InlayHintLabel::LabelParts(_) => None,
+        })
+        .collect()
+}
+
+#[test]
+fn open_modules_are_read_from_their_text() {
+    // The root doesn't exist, so the module is only found because it's open.
+    let root = std::env::temp_dir().join("luahint-open-modules");
+    let modules = Arc::new(Modules::default());
+    modules.set_roots(vec![root.clone()]);
+    let source = "local m = require(\"mod\")\nm.f(1, 2)\n";
+
+    let path = root.join("mod.lua");
+    let text = "local M = {}\nfunction M.f(a, b) end\nreturn M\n";
+    modules.set_open(path.clone(), 1, Rope::from(text));
+    assert_eq!(labels(source, &modules), vec!["a:", "b:"]);
+
+    // Unsaved edits are seen by the files requiring the module.
+    let text = "local M = {}\nfunction M.f(x, y) end\nreturn M\n";
+    modules.set_open(path.clone(), 2, Rope::from(text));
+    assert_eq!(labels(source, &modules), vec!["x:", "y:"]);
+
+    modules.close(&path);
+    assert_eq!(labels(source, &modules), Vec::<String>::new());
+}
+
+#[test]
+fn setmetatable_modules() {
+    let root = std::env::temp_dir().join("luahint-setmetatable-modules");
+    let modules = Arc::new(Modules::default());
+    modules.set_roots(vec![root.clone()]);
+    let text = "\
+local base = {}
+local M = setmetatable({}, { __index = base })
+function M.greet(name) end
+return M
+";
+    modules.set_open(root.join("greeter.lua"), 1, Rope::from(text));
+    let source = "local greeter = require(\"greeter\")\ngreeter.greet(\"x\")\n";
+    assert_eq!(labels(source, &modules), vec!["name:"]);
+}
+
+#[test]
+fn required_module_fields() {
+    let root = std::env::temp_dir().join("luahint-required-module-fields");
+    let modules = Arc::new(Modules::default());
+    modules.set_roots(vec![root.clone()]);
+    let text = "local M = {}\nfunction M.format(fmt, value) end\nreturn M\n";
+    modules.set_open(root.join("util.lua"), 1, Rope::from(text));
+
+    let source = "local fmt = require(\"util\").format\nfmt(\"%d\", 1)\n";
+    assert_eq!(labels(source, &modules), vec!["fmt:", "value:"]);
+    // Fields the module doesn't export, and modules that can't be found, bind nothing.
+    let source = "local fmt = require(\"util\").missing\nfmt(\"%d\", 1)\n";
+    assert_eq!(labels(source, &modules), Vec::<String>::new());
+    let source = "local fmt = require(\"missing\").format\nfmt(\"%d\", 1)\n";
+    assert_eq!(labels(source, &modules), Vec::<String>::new());
+}
+
+#[test]
+fn methods_called_on_required_modules() {
+    let root = std::env::temp_dir().join("luahint-methods-called-on-required-modules");
+    let modules = Arc::new(Modules::default());
+    modules.set_roots(vec![root.clone()]);
+    let text = "\
+local M = {}
+function M:connect(host, port) end
+function M.close(self, force) end
+return M
+";
+    modules.set_open(root.join("socket.lua"), 1, Rope::from(text));
+    let source = "\
+require(\"socket\"):connect(\"localhost\", 80)
+require(\"socket\"):close(true)
+require(\"missing\"):connect(\"localhost\", 80)
+";
+    assert_eq!(labels(source, &modules), vec!["host:", "port:", "force:"]);
+}
+
+#[test]
+fn edits_only_affect_dependents() {
+    let root = std::env::temp_dir().join("luahint-edits-only-affect-dependents");
+    let modules = Arc::new(Modules::default());
+    modules.set_roots(vec![root.clone()]);
+    let text = "local M = {}\nfunction M.f(a) end\nreturn M\n";
+    modules.set_open(root.join("util.lua"), 1, Rope::from(text));
+    modules.set_open(root.join("other.lua"), 1, Rope::from(text));
+    let ast = full_moon::parse("local util = require(\"util\")\nutil.f(1)\n").unwrap();
+    let manager = ScopeManager::new(ast, Arc::default(), modules.clone()).build();
+    assert!(manager.dependencies_current());
+
+    modules.set_open(root.join("other.lua"), 2, Rope::from(text));
+    assert!(manager.dependencies_current());
+    modules.set_open(root.join("util.lua"), 2, Rope::from(text));
+    assert!(!manager.dependencies_current());
+}
@@ -0,0 +1,59 @@
+//! Conversion of LSP positions, which count UTF-16 code units, to byte offsets, and the edits
+//! made with them.
+
+use crop::Rope;
+use lsp_types::{Position, Range, TextDocumentContentChangeEvent};
+use luahint::lsp::{apply_changes, position_to_byte};
+
+fn byte(text: &Rope, line: u32, character: u32) -> usize {
+    position_to_byte(text, Position { line, character })
+}
+
+#[test]
+fn multibyte_characters() {
+    // `é` is two bytes and one code unit, `😀` four bytes and two code units.
+    let text = Rope::from("a = 'é😀'\nb");
+    assert_eq!(byte(&text, 0, 5), 5);
+    assert_eq!(byte(&text, 0, 6), 7);
+    assert_eq!(byte(&text, 0, 8), 11);
+    assert_eq!(byte(&text, 1, 0), 13);
+}
+
+#[test]
+fn clamps_past_the_end() {
+    let text = Rope::from("a = 'é😀'\nb");
+    assert_eq!(byte(&text, 0, 100), 12);
+    assert_eq!(byte(&text, 5, 0), text.byte_len());
+}
+
+fn change(start: (u32, u32), end: (u32, u32), text: &str) -> TextDocumentContentChangeEvent {
+    let position = |(line, character): (u32, u32)| Position { line, character };
+    TextDocumentContentChangeEvent {
+        range: Some(Range {
+            start: position(start),
+            end: position(end),
+        }),
+        range_length: None,
+        text: text.to_string(),
+    }
+}
+
+#[test]
+fn changes_apply_in_order() {
+    // The second change's line already counts the line inserted by the first.
+    let mut text = Rope::from("local a = 1\nlocal b = 2\n");
+    let changes = vec![
+        change((0, 0), (0, 0), "-- x\n"),
+        change((2, 6), (2, 7), "c"),
+    ];
+    assert_eq!(apply_changes(&mut text, changes), 0);
+    assert_eq!(text.to_string(), "-- x\nlocal a = 1\nlocal c = 2\n");
+}
+
+#[test]
+fn changes_from_the_end() {
+    let mut text = Rope::from("local a = 1\nlocal b = 2\n");
+    let changes = vec![change((1, 6), (1, 7), "y"), change((0, 6), (0, 7), "x")];
+    assert_eq!(apply_changes(&mut text, changes), 6);
+    assert_eq!(text.to_string(), "local x = 1\nlocal y = 2\n");
+}
@@ -0,0 +1,134 @@
+//! Reuse of a previous parse after edits.
+
+use std::sync::Arc;
+
+use full_moon::ast::Ast;
+use full_moon::node::Node;
+use full_moon::tokenizer::Position;
+use luahint::reparse::Reparse;
+use luahint::scope::ScopeManager;
+
+/// The text and position of every token, ignoring trivia.
+fn tokens(ast: &Ast) -> Vec<(String, Option<Position>)> {
+    ast.nodes()
+        .tokens()
+        .map(|token| (token.token().to_string(), token.start_position()))
+        .collect()
+}
+
+/// The positions of the hints for `ast`, as emitted.
+fn hint_positions(ast: Arc<Ast>) -> Vec<(u32, u32)> {
+    ScopeManager::new(ast, Arc::default(), Arc::default())
+        .build()
+        .hints_within(lsp_types::Range::default())
+        .into_iter()
+        .map(|hint| (hint.position.line, hint.position.character))
+        .collect()
+}
+
+fn reparse(before: &str, after: &str, edited_from: usize) -> Option<Arc<Ast>> {
+    let old = Arc::new(full_moon::parse(before).unwrap());
+    Reparse::Edited(old, edited_from).parse(after)
+}
+
+#[test]
+fn spliced_positions_match_a_full_parse() {
+    let before = "local function f(a, b) end\nf(1, 2)\nf(3, 4)\n";
+    let after = "local function f(a, b) end\nf(1, 2)\nf(\"é\", 4)\nf(5, 6)\n";
+    let edited_from = before.find("f(3").unwrap();
+    let ast = reparse(before, after, edited_from).unwrap();
+    assert_eq!(tokens(&ast), tokens(&full_moon::parse(after).unwrap()));
+}
+
+#[test]
+fn keeps_statements_before_a_syntax_error() {
+    let before = "local function f(a) end\nf(1)\n";
+    let after = "local function f(a) end\nf(1)\nif x then\n";
+    let ast = reparse(before, after, before.len()).unwrap();
+    assert_eq!(ast.nodes().stmts().count(), 2);
+}
+
+#[test]
+fn edit_in_first_statement_parses_everything() {
+    let before = "local function f(a) end\nf(1)\n";
+    let after = "local function f(a, b) end\nf(1)\n";
+    let edited_from = before.find(')').unwrap();
+    let ast = reparse(before, after, edited_from).unwrap();
+    assert_eq!(tokens(&ast), tokens(&full_moon::parse(after).unwrap()));
+}
+
+#[test]
+fn recovers_functions_with_broken_bodies() {
+    let text = "\
+local function f(a, b)
+  local x =
+end
+local function g(c) end
+f(1, 2)
+g(3)
+";
+    let ast = Reparse::Full.parse(text).unwrap();
+    assert_eq!(ast.nodes().stmts().count(), 4);
+    assert_eq!(hint_positions(ast), vec![(5, 3), (5, 6), (6, 3)]);
+}
+
+#[test]
+fn recovers_edited_functions_with_broken_bodies() {
+    let before = "local function f(a) end\nf(1)\n";
+    let after = "local function f(a) end\nf(1)\nlocal function g(b)\n  if\nend\ng(2)\n";
+    let ast = reparse(before, after, before.len()).unwrap();
+    assert_eq!(ast.nodes().stmts().count(), 4);
+    assert_eq!(hint_positions(ast), vec![(2, 3), (6, 3)]);
+}
+
+#[test]
+fn reparsed_trivia_is_moved_into_place() {
+    let before = "local s = [[\nlong\n]] -- note\nf(1)\n";
+    let after = "local s = [[\nlong\n]] -- note\n--[[ block\ncomment ]] f(2)\n";
+    let ast = reparse(before, after, before.find("f(1)").unwrap()).unwrap();
+    let trivia = |ast: &Ast| {
+        ast.nodes()
+            .tokens()
+            .flat_map(|token| token.leading_trivia().chain(token.trailing_trivia()))
+            .map(|trivia| {
+                (
+                    trivia.to_string(),
+                    trivia.start_position(),
+                    trivia.end_position(),
+                )
+            })
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(tokens(&ast), tokens(&full_moon::parse(after).unwrap()));
+    assert_eq!(trivia(&ast), trivia(&full_moon::parse(after).unwrap()));
+}
+
+#[test]
+fn recovers_around_each_error() {
+    let text = "\
+function f(a)
+local y = a
+end
+x = = 1
+f(1)
+y = = 2
+f(2)
+";
+    let ast = Reparse::Full.parse(text).unwrap();
+    assert_eq!(ast.nodes().stmts().count(), 3);
+    assert_eq!(hint_positions(ast), vec![(5, 3), (7, 3)]);
+}
+
+#[test]
+fn recovers_blocks_left_open() {
+    let text = "\
+local function f(a, b)
+if a then
+local y = a
+end
+f(1, 2)
+";
+    let ast = Reparse::Full.parse(text).unwrap();
+    assert_eq!(ast.nodes().stmts().count(), 2);
+    assert_eq!(hint_positions(ast), vec![(5, 3), (5, 6)]);
+}
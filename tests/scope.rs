@@ -0,0 +1,169 @@
+//! Invariants of the scope tree built for a document.
+
+use std::sync::Arc;
+
+use full_moon::ast::Stmt;
+use luahint::config::Config;
+use luahint::scope::ScopeManager;
+
+fn problems(source: &str) -> Vec<String> {
+    let ast = full_moon::parse(source).unwrap();
+    ScopeManager::new(ast, Arc::default(), Arc::default())
+        .build()
+        .validate()
+}
+
+/// The rendered scope tree for `source`, without the scope of bundled definitions.
+fn tree(source: &str) -> String {
+    let ast = full_moon::parse(source).unwrap();
+    let config = Config::from_value(serde_json::json!({ "stdlib": false })).0;
+    ScopeManager::new(ast, Arc::new(config), Arc::default())
+        .build()
+        .to_string()
+}
+
+#[test]
+fn nested_dotted_declarations() {
+    let source = "\
+local a = { b = {} }
+local function outer()
+  function a.b.c(x)
+    function a.b.d(y) end
+  end
+  local t = {}
+  function t.f() end
+end
+";
+    assert_eq!(problems(source), Vec::<String>::new());
+}
+
+#[test]
+fn functions_assigned_together() {
+    let source = "\
+local f, g = function(a) end, function(b) end
+x, y = function() local h = function() end end, function() end
+";
+    assert_eq!(problems(source), Vec::<String>::new());
+}
+
+#[test]
+fn scopes_of_blocks_after_build() {
+    let ast = full_moon::parse("local function f()\n  local x = 1\nend\n").unwrap();
+    // `build` moves the manager, but the blocks it recorded stay where they are.
+    let manager = ScopeManager::new(ast, Arc::default(), Arc::default()).build();
+    let chunk = manager.ast().nodes();
+    let Some(Stmt::LocalFunction(f)) = chunk.stmts().next() else {
+        panic!("expected a local function");
+    };
+    let chunk_scope = manager.get_scope_id(chunk).unwrap();
+    let body_scope = manager.get_scope_id(f.body().block()).unwrap();
+    assert_ne!(chunk_scope, body_scope);
+    assert_eq!(
+        manager.get_scope(body_scope).unwrap().parent,
+        Some(chunk_scope)
+    );
+}
+
+#[test]
+fn scopes_by_block_range() {
+    let source = "do local a = 1 end\ndo local b = 2 end\n";
+    let ast = full_moon::parse(source).unwrap();
+    let manager = ScopeManager::new(ast, Arc::default(), Arc::default()).build();
+    let range = |block: &str| {
+        let start = source.find(block).unwrap();
+        (start, start + block.len())
+    };
+    let first = manager.get_scope_id_by_range(range("local a = 1")).unwrap();
+    let second = manager.get_scope_id_by_range(range("local b = 2")).unwrap();
+    assert_ne!(first, second);
+    let names = |scope| {
+        let scope = manager.get_scope(scope).unwrap();
+        scope.var_names.keys().cloned().collect::<Vec<_>>()
+    };
+    assert_eq!(names(first), vec!["a"]);
+    assert_eq!(names(second), vec!["b"]);
+}
+
+#[test]
+fn scopes_of_copied_blocks() {
+    let ast = full_moon::parse("local x = 1\n").unwrap();
+    let manager = ScopeManager::new(ast.clone(), Arc::default(), Arc::default()).build();
+    assert_eq!(
+        manager.get_scope_id(ast.nodes()),
+        manager.get_scope_id(manager.ast().nodes())
+    );
+    assert!(manager.get_scope_id(ast.nodes()).is_some());
+}
+
+#[cfg(feature = "trace")]
+#[test]
+fn trace_of_scope_operations() {
+    use luahint::scope::TraceEvent;
+
+    let ast = full_moon::parse("local function f(a) end\nf(1)\n").unwrap();
+    let mut manager = ScopeManager::new(ast, Arc::default(), Arc::default()).build();
+    let events = manager
+        .drain_trace()
+        .into_iter()
+        .map(|event| match event {
+            TraceEvent::OpenScope { name, .. } => {
+                format!("open {}", name.as_deref().unwrap_or("<block>"))
+            }
+            TraceEvent::CloseScope { .. } => "close".to_string(),
+            TraceEvent::AllocLocal { name, .. } => format!("local {name}"),
+            TraceEvent::Hint {
+                label,
+                line,
+                character,
+            } => format!("hint {label} {line}:{character}"),
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(
+        events,
+        vec![
+            "open <block>",
+            "local f",
+            "open f",
+            "close",
+            "hint a: 2:3",
+            "close"
+        ]
+    );
+    assert!(manager.drain_trace().is_empty());
+}
+
+#[test]
+fn swapping_parameters_stays_in_the_function() {
+    // The swap rebinds the parameters, it doesn't declare globals named after them.
+    let source = "function f(a, b) a, b = b, a end\nf(b, a)\n";
+    assert_eq!(
+        tree(source),
+        "\
+global
+  f: function/2(a, b)
+  <block>
+    f
+      a: reference
+      b: reference
+"
+    );
+    assert_eq!(problems(source), Vec::<String>::new());
+}
+
+#[test]
+fn rendered_scope_tree() {
+    let source = "local function add(a, b) end\nlocal t = {}\nlocal n = 1\nadd(1, 2)\n";
+    assert_eq!(
+        tree(source),
+        "\
+global
+  <block>
+    add: function/2(a, b)
+    t: table
+    n: value
+    add
+      a: parameter
+      b: parameter
+"
+    );
+}
@@ -0,0 +1,282 @@
+//! Requests handled by the language server. No client is attached, so whatever the server sends to
+//! the client is dropped.
+
+use std::time::Duration;
+
+use lsp_types::{
+    DidChangeConfigurationParams, DidChangeTextDocumentParams, DidOpenTextDocumentParams,
+    DocumentSymbolParams, FoldingRangeParams, InitializeParams, InlayHint, InlayHintLabel,
+    InlayHintOptions, InlayHintParams, InlayHintServerCapabilities, OneOf, Position, Range,
+    TextDocumentContentChangeEvent, TextDocumentIdentifier, TextDocumentItem, Url,
+    VersionedTextDocumentIdentifier, WorkDoneProgressOptions, WorkDoneProgressParams,
+};
+use luahint::lsp::Backend;
+use tower_lsp::{LanguageServer, LspService};
+
+fn uri() -> Url {
+    uri_of("main")
+}
+
+fn uri_of(name: &str) -> Url {
+    Url::parse(&format!("file:///luahint-test/{name}.lua")).unwrap()
+}
+
+async fn open(backend: &Backend, text: &str) {
+    open_at(backend, uri(), text).await;
+}
+
+async fn open_at(backend: &Backend, uri: Url, text: &str) {
+    backend
+        .did_open(DidOpenTextDocumentParams {
+            text_document: TextDocumentItem {
+                uri,
+                language_id: "lua".to_string(),
+                version: 1,
+                text: text.to_string(),
+            },
+        })
+        .await;
+}
+
+/// Replaces the whole text of the document.
+async fn change(backend: &Backend, version: i32, text: &str) {
+    backend
+        .did_change(DidChangeTextDocumentParams {
+            text_document: VersionedTextDocumentIdentifier {
+                uri: uri(),
+                version,
+            },
+            content_changes: vec![TextDocumentContentChangeEvent {
+                range: None,
+                range_length: None,
+                text: text.to_string(),
+            }],
+        })
+        .await;
+}
+
+async fn inlay_hints(backend: &Backend, range: Range) -> Vec<InlayHint> {
+    inlay_hints_at(backend, uri(), range).await
+}
+
+async fn inlay_hints_at(backend: &Backend, uri: Url, range: Range) -> Vec<InlayHint> {
+    backend
+        .inlay_hint(InlayHintParams {
+            work_done_progress_params: WorkDoneProgressParams::default(),
+            text_document: TextDocumentIdentifier { uri },
+            range,
+        })
+        .await
+        .unwrap()
+        .unwrap_or_default()
+}
+
+/// The positions of the hints the server returns for `range`, as emitted.
+async fn hint_positions(backend: &Backend, range: Range) -> Vec<(u32, u32)> {
+    inlay_hints(backend, range)
+        .await
+        .into_iter()
+        .map(|hint| (hint.position.line, hint.position.character))
+        .collect()
+}
+
+/// The labels of the hints the server returns for the whole document.
+async fn hint_labels(backend: &Backend) -> Vec<String> {
+    hint_labels_at(backend, uri()).await
+}
+
+async fn hint_labels_at(backend: &Backend, uri: Url) -> Vec<String> {
+    inlay_hints_at(backend, uri, Range::default())
+        .await
+        .into_iter()
+        .filter_map(|hint| match hint.label {
+            InlayHintLabel::String(label) => Some(label),
+            InlayHintLabel::LabelParts(_) => None,
+        })
+        .collect()
+}
+
+#[tokio::test]
+async fn hints_within_requested_range() {
+    let (service, _) = LspService::new(Backend::new);
+    let backend = service.inner();
+    open(backend, "local function f(a) end\nf(1)\nf(2)\n").await;
+
+    let line = Range::new(Position::new(2, 0), Position::new(3, 0));
+    assert_eq!(hint_positions(backend, line).await, vec![(3, 3)]);
+    // A zero-width range at the cursor asks for the whole document.
+    let cursor = Range::new(Position::new(1, 2), Position::new(1, 2));
+    assert_eq!(hint_positions(backend, cursor).await, vec![(2, 3), (3, 3)]);
+}
+
+#[tokio::test]
+async fn shutdown_drops_pending_work() {
+    let (service, _) = LspService::new(Backend::new);
+    let backend = service.inner();
+    backend
+        .initialize(InitializeParams {
+            initialization_options: Some(serde_json::json!({ "checkArity": true })),
+            ..Default::default()
+        })
+        .await
+        .unwrap();
+    open(backend, "local function f(a) end\nf()\n").await;
+
+    // The edit's diagnostics wait for the document to settle, which shutdown doesn't.
+    let (_, shutdown) = tokio::time::timeout(Duration::from_millis(25), async {
+        tokio::join!(
+            change(backend, 2, "local function f(a) end\nf(1)\n"),
+            backend.shutdown()
+        )
+    })
+    .await
+    .expect("shutdown waited for pending work");
+    shutdown.unwrap();
+}
+
+#[tokio::test]
+async fn disabled_server_skips_parsing() {
+    let (service, _) = LspService::new(Backend::new);
+    let backend = service.inner();
+    backend
+        .initialize(InitializeParams {
+            initialization_options: Some(serde_json::json!({
+                "enabled": false,
+                "labelFormat": "{name} =",
+            })),
+            ..Default::default()
+        })
+        .await
+        .unwrap();
+    open(backend, "local function f(a) end\nf(1)\n").await;
+    assert_eq!(hint_labels(backend).await, Vec::<String>::new());
+    assert_eq!(backend.parses(), 0);
+
+    // Only the setting that changed is sent, the others keep their value.
+    backend
+        .did_change_configuration(DidChangeConfigurationParams {
+            settings: serde_json::json!({ "luahint": { "enabled": true } }),
+        })
+        .await;
+    assert_eq!(hint_labels(backend).await, vec!["a ="]);
+    assert_eq!(backend.parses(), 1);
+}
+
+#[tokio::test]
+async fn hints_follow_concurrent_edits() {
+    let (service, _) = LspService::new(Backend::new);
+    let backend = service.inner();
+    open(backend, "local function f(a) end\nf(1)\n").await;
+    // This caches the pass over the first version.
+    assert_eq!(hint_labels(backend).await, vec!["a:"]);
+
+    // A request made along with an edit gets the hints of the edited text, not the cached ones.
+    let (_, labels) = tokio::join!(
+        change(backend, 2, "local function f(b) end\nf(1)\n"),
+        hint_labels(backend)
+    );
+    assert_eq!(labels, vec!["b:"]);
+}
+
+#[tokio::test]
+async fn inlay_hints_advertise_progress() {
+    let (service, _) = LspService::new(Backend::new);
+    let result = service
+        .inner()
+        .initialize(InitializeParams::default())
+        .await
+        .unwrap();
+    assert_eq!(
+        result.capabilities.inlay_hint_provider,
+        Some(OneOf::Right(InlayHintServerCapabilities::Options(
+            InlayHintOptions {
+                work_done_progress_options: WorkDoneProgressOptions {
+                    work_done_progress: Some(true)
+                },
+                resolve_provider: None,
+            }
+        )))
+    );
+}
+
+#[tokio::test]
+async fn refresh_command_is_advertised() {
+    let (service, _) = LspService::new(Backend::new);
+    let result = service
+        .inner()
+        .initialize(InitializeParams::default())
+        .await
+        .unwrap();
+    let commands = result
+        .capabilities
+        .execute_command_provider
+        .map(|provider| provider.commands)
+        .unwrap_or_default();
+    assert!(
+        commands.iter().any(|command| command == "luahint.refresh"),
+        "{commands:?}"
+    );
+}
+
+#[tokio::test]
+async fn least_recently_hinted_parses_are_dropped() {
+    let (service, _) = LspService::new(Backend::new);
+    let backend = service.inner();
+    backend
+        .initialize(InitializeParams {
+            initialization_options: Some(serde_json::json!({ "maxCachedDocuments": 2 })),
+            ..Default::default()
+        })
+        .await
+        .unwrap();
+    for name in ["a", "b", "c"] {
+        let text = format!("local function f({name}) end\nf(1)\n");
+        open_at(backend, uri_of(name), &text).await;
+    }
+    for name in ["a", "b", "c"] {
+        hint_labels_at(backend, uri_of(name)).await;
+    }
+    assert_eq!(backend.parses(), 3);
+
+    // `a` was hinted longest ago, so caching `c` dropped its parse, but not `b`'s.
+    assert_eq!(hint_labels_at(backend, uri_of("b")).await, vec!["b:"]);
+    assert_eq!(backend.parses(), 3);
+    // The text of `a` is kept, so it's parsed again.
+    assert_eq!(hint_labels_at(backend, uri_of("a")).await, vec!["a:"]);
+    assert_eq!(backend.parses(), 4);
+}
+
+#[tokio::test]
+async fn large_documents_are_never_parsed() {
+    let (service, _) = LspService::new(Backend::new);
+    let backend = service.inner();
+    backend
+        .initialize(InitializeParams {
+            initialization_options: Some(serde_json::json!({ "maxFileSizeBytes": 16 })),
+            ..Default::default()
+        })
+        .await
+        .unwrap();
+    open(backend, "local function f(a) end\nf(1)\n").await;
+    let text_document = TextDocumentIdentifier { uri: uri() };
+    let symbols = backend
+        .document_symbol(DocumentSymbolParams {
+            text_document: text_document.clone(),
+            work_done_progress_params: WorkDoneProgressParams::default(),
+            partial_result_params: Default::default(),
+        })
+        .await
+        .unwrap();
+    assert!(symbols.is_none());
+    let ranges = backend
+        .folding_range(FoldingRangeParams {
+            text_document,
+            work_done_progress_params: WorkDoneProgressParams::default(),
+            partial_result_params: Default::default(),
+        })
+        .await
+        .unwrap();
+    assert!(ranges.is_none());
+    assert_eq!(hint_labels(backend).await, Vec::<String>::new());
+    assert_eq!(backend.parses(), 0);
+}
@@ -0,0 +1,61 @@
+//! Signatures shown while typing the arguments of a call.
+
+use std::sync::Arc;
+
+use luahint::scope::ScopeManager;
+
+/// The signature and active parameter of the call around the first occurrence of `at`.
+fn signature_help(source: &str, at: &str) -> Option<(String, usize)> {
+    let ast = full_moon::parse(source).unwrap();
+    let manager = ScopeManager::new(ast, Arc::default(), Arc::default()).build();
+    let offset = source.find(at).unwrap();
+    let call = manager.call_at(offset)?;
+    Some((call.signature.clone(), call.active_parameter(offset)))
+}
+
+fn help(signature: &str, active: usize) -> Option<(String, usize)> {
+    Some((signature.to_string(), active))
+}
+
+#[test]
+fn innermost_call_wins() {
+    let source = "\
+local function outer(a, b) end
+local function inner(x, y) end
+outer(1, inner(2, 3))
+";
+    assert_eq!(
+        signature_help(source, "1,"),
+        help("function outer(a, b)", 0)
+    );
+    assert_eq!(
+        signature_help(source, "inner(2"),
+        help("function outer(a, b)", 1)
+    );
+    assert_eq!(
+        signature_help(source, "2,"),
+        help("function inner(x, y)", 0)
+    );
+    assert_eq!(
+        signature_help(source, "3)"),
+        help("function inner(x, y)", 1)
+    );
+}
+
+#[test]
+fn method_call() {
+    let source = "\
+local t = {}
+function t:move(x, y) end
+t:move(1, 2)
+";
+    assert_eq!(
+        signature_help(source, "2)"),
+        help("function t:move(x, y)", 1)
+    );
+}
+
+#[test]
+fn unknown_function() {
+    assert_eq!(signature_help("print(1, 2)\n", "2"), None);
+}
@@ -0,0 +1,49 @@
+//! The outline of functions reported as document symbols.
+
+use lsp_types::DocumentSymbol;
+use luahint::symbols::document_symbols;
+
+/// The names of `symbols` and of their children, as `name(child, ...)`.
+fn outline(symbols: &[DocumentSymbol]) -> Vec<String> {
+    symbols
+        .iter()
+        .map(|symbol| match &symbol.children {
+            Some(children) => format!("{}({})", symbol.name, outline(children).join(", ")),
+            None => symbol.name.clone(),
+        })
+        .collect()
+}
+
+#[test]
+fn nested_functions() {
+    let source = "\
+local function outer()
+  local function inner() end
+  local helper = function() end
+  return function()
+    local function deep() end
+  end
+end
+function M.f(a) end
+local t = { g = function(b) end }
+";
+    let symbols = document_symbols(&full_moon::parse(source).unwrap());
+    assert_eq!(
+        outline(&symbols),
+        vec!["outer(inner, helper, deep)", "M.f", "g"]
+    );
+    assert_eq!(symbols[1].detail.as_deref(), Some("(a)"));
+    assert_eq!(symbols[1].selection_range.start.line, 7);
+    assert_eq!(symbols[1].selection_range.start.character, 9);
+}
+
+#[test]
+fn names_without_comments_or_whitespace() {
+    let source = "\
+function M --[[ module ]] . f() end
+M . g = function() end
+t [ \"h\" ] = function() end
+";
+    let symbols = document_symbols(&full_moon::parse(source).unwrap());
+    assert_eq!(outline(&symbols), vec!["M.f", "M.g", "t[\"h\"]"]);
+}
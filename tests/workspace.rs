@@ -0,0 +1,92 @@
+//! Indexing of the modules in the workspace folders.
+
+use std::path::Path;
+
+use lsp_types::{Position, Range};
+use luahint::workspace::{index_source, WorkspaceIndex};
+
+fn write(path: &Path, text: &str) {
+    std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+    std::fs::write(path, text).unwrap();
+}
+
+#[test]
+fn exported_functions() {
+    let source = "\
+local M = {}
+function M.f(a, b) end
+function M:m(x) end
+local function helper() end
+return M
+";
+    let functions = index_source(source).unwrap();
+    let f = &functions["f"];
+    assert_eq!(f.params, vec!["a", "b"]);
+    assert_eq!(
+        f.range,
+        Range {
+            start: Position::new(1, 9),
+            end: Position::new(1, 12),
+        }
+    );
+    assert_eq!(functions[":m"].params, vec!["x"]);
+    assert!(!functions.contains_key("helper"));
+}
+
+#[test]
+fn scan_skips_ignored_directories() {
+    let root = std::env::temp_dir().join(format!("luahint-index-{}", std::process::id()));
+    let module = "local M = {}\nfunction M.f(a) end\nreturn M\n";
+    write(&root.join("lua/app/init.lua"), module);
+    write(&root.join("lua/app/util.lua"), module);
+    write(&root.join("node_modules/dep.lua"), module);
+    write(&root.join(".git/hook.lua"), module);
+    write(&root.join("vendor/lib.lua"), module);
+    write(
+        &root.join(".luarc.json"),
+        r#"{ "workspace.ignoreDir": ["vendor"] }"#,
+    );
+
+    let index = WorkspaceIndex::default();
+    index.scan(&[root.clone()]);
+    let found = ["app", "app.util", "node_modules.dep", "dep", "vendor.lib"]
+        .into_iter()
+        .filter(|name| index.module(name).is_some())
+        .collect::<Vec<_>>();
+    let (path, f) = index.function("app.util", "f").unwrap();
+    std::fs::remove_dir_all(&root).unwrap();
+
+    assert_eq!(found, vec!["app", "app.util"]);
+    assert_eq!(path, root.join("lua/app/util.lua"));
+    assert_eq!(f.params, vec!["a"]);
+}
+
+#[test]
+fn update_file_follows_the_disk() {
+    let root = std::env::temp_dir().join(format!("luahint-update-{}", std::process::id()));
+    let path = root.join("lua/app.lua");
+    write(&path, "local M = {}\nfunction M.f(a) end\nreturn M\n");
+    let index = WorkspaceIndex::default();
+    let roots = [root.clone()];
+    index.scan(&roots);
+
+    write(&path, "local M = {}\nfunction M.f(a, b) end\nreturn M\n");
+    index.update_file(&roots, &path);
+    let changed = index.function("app", "f").map(|(_, f)| f.params);
+    // A file that doesn't parse keeps its last entry.
+    write(&path, "local M = {}\nfunction M.f(\n");
+    index.update_file(&roots, &path);
+    let broken = index.function("app", "f").map(|(_, f)| f.params);
+    // Files in skipped directories aren't indexed.
+    let dependency = root.join("node_modules/dep.lua");
+    write(&dependency, "local M = {}\nfunction M.f(a) end\nreturn M\n");
+    index.update_file(&roots, &dependency);
+    let skipped = index.module("node_modules.dep").is_some();
+    std::fs::remove_dir_all(&root).unwrap();
+    index.update_file(&roots, &path);
+
+    assert_eq!(changed, Some(vec!["a".to_string(), "b".to_string()]));
+    assert_eq!(broken, Some(vec!["a".to_string(), "b".to_string()]));
+    assert!(!skipped);
+    assert!(index.module("app").is_none());
+}